@@ -0,0 +1,71 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{EPOCH_RECORD_SEED, EpochRecord, Pool};
+use crate::utils::{accrue_within_budget, emission_for, AdvanceEpochEvent, StakingError};
+
+/// @dev Permissionless crank closing the epoch in progress once it has ended
+/// @dev Snapshots the shares held for the whole epoch and its rewards into an `EpochRecord`,
+/// then starts the next epoch where this one ended
+pub fn _advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    require!(pool.epoch_duration > 0i64, StakingError::EpochModeDisabled);
+
+    let now = Clock::get()?.unix_timestamp;
+    let end_time = pool.epoch_start.checked_add(pool.epoch_duration).ok_or(StakingError::Overflow)?;
+    require!(now >= end_time, StakingError::EpochNotEnded);
+
+    // Nothing is emitted for an epoch nobody held shares through
+    let reward_amount = if pool.epoch_eligible_shares > 0u128 {
+        let emitted = emission_for(pool, pool.epoch_start, end_time)?;
+        accrue_within_budget(pool, emitted)?
+    } else {
+        0u128
+    };
+    let reward_amount: u64 = reward_amount.try_into().map_err(|_| StakingError::Overflow)?;
+
+    let epoch_record = &mut ctx.accounts.epoch_record;
+    epoch_record.pool = pool.key();
+    epoch_record.epoch = pool.current_epoch;
+    epoch_record.start_time = pool.epoch_start;
+    epoch_record.end_time = end_time;
+    epoch_record.eligible_shares = pool.epoch_eligible_shares;
+    epoch_record.reward_amount = reward_amount;
+    epoch_record.claimed_amount = 0u64;
+    epoch_record.bump = ctx.bumps.epoch_record;
+
+    // Every current share is held for the whole next epoch unless unstaked
+    pool.current_epoch = pool.current_epoch.checked_add(1u64).ok_or(StakingError::Overflow)?;
+    pool.epoch_start = end_time;
+    pool.epoch_eligible_shares = pool.total_shares;
+
+    emit!(AdvanceEpochEvent {
+        pool: pool.key(),
+        epoch: epoch_record.epoch,
+        eligible_shares: epoch_record.eligible_shares,
+        reward_amount,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct AdvanceEpoch<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = cranker,
+        space = 8 + EpochRecord::INIT_SPACE,
+        seeds = [EPOCH_RECORD_SEED.as_bytes(), pool.key().as_ref(), &pool.current_epoch.to_le_bytes()],
+        bump
+    )]
+    pub epoch_record: Account<'info, EpochRecord>,
+
+    pub system_program: Program<'info, System>,
+}
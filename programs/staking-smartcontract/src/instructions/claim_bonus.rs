@@ -0,0 +1,110 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::states::{BONUS_DISTRIBUTION_SEED, BONUS_VAULT_SEED, MAX_BONUS_CLAIMS, POOL_SEED, BonusDistribution, Pool};
+use crate::utils::{check_deadline, leaf_hash, verify_proof, ClaimBonusEvent, StakingError};
+
+/// @dev Function to claim a bonus from the pool's merkle distribution -- ONLY the claimant of the leaf
+/// @param `index` Index of the claimant's leaf, each index can be claimed once
+/// @param `amount` Bonus granted by the leaf
+/// @param `proof` Sibling hashes from the leaf up to the root
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _claim_bonus(
+    ctx: Context<ClaimBonus>,
+    index: u32,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let now = Clock::get()?.unix_timestamp;
+    let claimant = &ctx.accounts.claimant;
+    let pool = &ctx.accounts.pool;
+    let bonus_distribution = &mut ctx.accounts.bonus_distribution;
+    let reward_mint = &ctx.accounts.reward_mint;
+
+    require!(now < bonus_distribution.expiry, StakingError::BonusExpired);
+    require!((index as usize) < MAX_BONUS_CLAIMS, StakingError::InvalidProof);
+
+    let byte = index as usize / 8;
+    let bit = 1u8 << (index % 8);
+    require!(bonus_distribution.claimed_bitmap[byte] & bit == 0u8, StakingError::BonusAlreadyClaimed);
+
+    let leaf = leaf_hash(index, claimant.key().as_ref(), amount);
+    require!(verify_proof(&proof, &bonus_distribution.merkle_root, leaf), StakingError::InvalidProof);
+
+    bonus_distribution.claimed_bitmap[byte] |= bit;
+    bonus_distribution.claimed_amount = bonus_distribution.claimed_amount.checked_add(amount).ok_or(StakingError::Overflow)?;
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Transfer from bonus_vault --> claimant
+    let cpi_accounts = TransferChecked {
+        mint: reward_mint.to_account_info(),
+        from: ctx.accounts.bonus_vault.to_account_info(),
+        to: ctx.accounts.claimant_reward_ata.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::transfer_checked(cpi_context, amount, reward_mint.decimals)?;
+
+    emit!(ClaimBonusEvent {
+        pool: pool.key(),
+        claimant: claimant.key(),
+        index,
+        amount,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct ClaimBonus<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [BONUS_DISTRIBUTION_SEED.as_bytes(), pool.key().as_ref()],
+        bump = bonus_distribution.bump,
+    )]
+    pub bonus_distribution: Account<'info, BonusDistribution>,
+
+    #[account(
+        mut,
+        seeds = [BONUS_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub bonus_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = claimant,
+        associated_token::mint = reward_mint,
+        associated_token::authority = claimant,
+        associated_token::token_program = token_program,
+    )]
+    pub claimant_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
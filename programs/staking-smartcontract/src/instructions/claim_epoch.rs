@@ -0,0 +1,170 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::instructions::{pay_reward, RewardCuts, RewardPayout};
+use crate::states::{
+    EPOCH_RECORD_SEED, POOL_SEED, REFERRER_SEED, REWARD_VESTING_SEED, REWARD_VESTING_VAULT_SEED, EpochRecord, Pool,
+    ReferrerAccount, RewardVesting, UserStake,
+};
+use crate::utils::{
+    ClaimEpochEvent, Rounding, StakingError, can_claim_position, check_deadline, epoch_shares_of, mul_div,
+    reward_recipient_for, take_reward_budget,
+};
+
+/// @dev Function to claim the rewards of a finished epoch -- by the owner or their claim delegate, or the holder
+/// of the position NFT
+/// @dev Epochs are claimed in order, each paying the position's share of the epoch's rewards
+/// to its reward recipient like `claim_reward` -- net of the referrer's cut and the protocol fee, and vested in vesting pools
+/// @param `epoch` The next epoch the position has not claimed yet
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _claim_epoch(ctx: Context<ClaimEpoch>, epoch: u64, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let user_stake = &mut ctx.accounts.user_stake;
+    let epoch_record = &mut ctx.accounts.epoch_record;
+
+    require!(epoch == user_stake.next_claim_epoch.max(1u64), StakingError::InvalidEpoch);
+
+    // The position's share of the epoch's rewards
+    let eligible_shares = epoch_shares_of(user_stake, epoch);
+    let reward = if epoch_record.eligible_shares > 0u128 {
+        mul_div(epoch_record.reward_amount as u128, eligible_shares, epoch_record.eligible_shares, Rounding::Down)?
+    } else {
+        0u128
+    };
+    let reward = take_reward_budget(pool, reward)?;
+    let reward_u64: u64 = reward.try_into().map_err(|_| StakingError::Overflow)?;
+
+    epoch_record.claimed_amount = epoch_record.claimed_amount.checked_add(reward_u64).ok_or(StakingError::Overflow)?;
+    user_stake.next_claim_epoch = epoch.checked_add(1u64).ok_or(StakingError::Overflow)?;
+
+    let payout = RewardPayout {
+        cuts: RewardCuts {
+            pool,
+            reward_mint: &ctx.accounts.reward_mint,
+            referrer_account: ctx.accounts.referrer_account.as_mut(),
+            referrer_bump: ctx.bumps.referrer_account,
+            treasury_reward_ata: ctx.accounts.treasury_reward_ata.as_ref(),
+            token_program: &ctx.accounts.token_program,
+        },
+        position: user_stake.key(),
+        recipient: ctx.accounts.reward_recipient.key(),
+        recipient_ata: &ctx.accounts.user_reward_ata,
+        reward_vesting: ctx.accounts.reward_vesting.as_mut(),
+        reward_vesting_bump: ctx.bumps.reward_vesting,
+        vesting_vault: ctx.accounts.vesting_vault.as_ref(),
+    };
+    let (user_reward, fee_amount) = pay_reward(payout, user_stake, reward, now)?;
+
+    emit!(ClaimEpochEvent {
+        pool: pool.key(),
+        user: user_stake.owner,
+        recipient: ctx.accounts.reward_recipient.key(),
+        epoch,
+        reward_claimed: user_reward,
+        fee_amount,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct ClaimEpoch<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(), stake_mint.key().as_ref()],
+        bump,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = user_stake.pool == pool.key() @ StakingError::InvalidPool,
+        constraint = can_claim_position(&user_stake, &user.key(), position_nft.as_deref()) @ StakingError::InvalidOwner,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    /// Required when the position is an NFT, the user's token account holding it
+    pub position_nft: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [EPOCH_RECORD_SEED.as_bytes(), pool.key().as_ref(), &epoch.to_le_bytes()],
+        bump = epoch_record.bump,
+    )]
+    pub epoch_record: Account<'info, EpochRecord>,
+
+    /// CHECK: receiver of the rewards, validated against the position
+    #[account(address = reward_recipient_for(&user_stake, &user.key()) @ StakingError::InvalidOwner)]
+    pub reward_recipient: UncheckedAccount<'info>,
+
+    /// CHECK: stake mint
+    pub stake_mint: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = reward_recipient,
+        associated_token::token_program = token_program,
+    )]
+    pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required when the pool vests claimed rewards
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardVesting::INIT_SPACE,
+        seeds = [
+            REWARD_VESTING_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user_stake.key().as_ref(),
+            reward_recipient.key().as_ref(),
+        ],
+        bump
+    )]
+    pub reward_vesting: Option<Account<'info, RewardVesting>>,
+
+    #[account(
+        mut,
+        seeds = [REWARD_VESTING_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub vesting_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the position has a referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ReferrerAccount::INIT_SPACE,
+        seeds = [REFERRER_SEED.as_bytes(), pool.key().as_ref(), user_stake.referrer.as_ref()],
+        bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// Required when the pool charges a protocol fee
+    #[account(
+        mut,
+        constraint = treasury_reward_ata.mint == pool.reward_mint,
+        constraint = treasury_reward_ata.owner == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub treasury_reward_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
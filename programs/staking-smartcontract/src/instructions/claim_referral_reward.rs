@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
+
+use crate::states::{POOL_SEED, REFERRER_SEED, Pool, ReferrerAccount};
+use crate::utils::{check_deadline, ClaimReferralRewardEvent, StakingError};
+
+/// @dev Function for a referrer to claim their accrued referral rewards
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _claim_referral_reward(ctx: Context<ClaimReferralReward>, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let pool = &ctx.accounts.pool;
+    let referrer_account = &mut ctx.accounts.referrer_account;
+
+    let reward = referrer_account.accrued_reward;
+    if reward == 0u64 {
+        return Ok(());
+    }
+
+    referrer_account.accrued_reward = 0u64;
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Prepare and call the mint function
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.reward_mint.to_account_info(),
+        to: ctx.accounts.referrer_reward_ata.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::mint_to(cpi_context, reward)?;
+
+    emit!(ClaimReferralRewardEvent {
+        pool: pool.key(),
+        referrer: ctx.accounts.referrer.key(),
+        reward_claimed: reward,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct ClaimReferralReward<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [REFERRER_SEED.as_bytes(), pool.key().as_ref(), referrer.key().as_ref()],
+        bump = referrer_account.bump,
+        has_one = referrer,
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+
+    #[account(mut)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = referrer,
+        associated_token::mint = reward_mint,
+        associated_token::authority = referrer,
+        associated_token::token_program = token_program,
+    )]
+    pub referrer_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
@@ -2,60 +2,244 @@ use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
 
-use crate::states::{POOL_SEED, Pool, UserStake};
-use crate::utils::{SCALING_FACTOR, StakingError, sync_reward_vars, user_pending_reward, ClaimRewardEvent};
+use crate::states::{
+    POOL_SEED, REFERRER_SEED, REWARD_VESTING_SEED, REWARD_VESTING_VAULT_SEED, Pool, ReferrerAccount, RewardVesting,
+    UserStake,
+};
+use crate::utils::{
+    ClaimRewardEvent, ReferralRewardEvent, StakingError, VestingTrancheEvent, add_vesting_tranche, bps_of,
+    can_claim_position, check_deadline, check_vesting_recipient, reward_recipient_for, reward_recipient_of, sync_reward_vars,
+    take_reward_budget, update_user_weight, user_pending_reward,
+};
 
-
-/// @dev Function to claim pending rewards
-pub fn _claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
+/// @dev Function to claim pending rewards -- by the owner or their claim delegate, or the holder of the position NFT
+/// @dev Rewards are paid to the position's reward recipient, or to the holder for NFT positions
+/// @dev More positions can be claimed at once through `remaining_accounts`, they must be claimable
+/// by the same signer and share the reward recipient and referrer of `user_stake`. NFT positions, and positions
+/// of vesting pools whose rewards vest per position, are claimed on their own
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _claim_reward<'info>(ctx: Context<'_, '_, 'info, 'info, ClaimReward<'info>>, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
     require!(!ctx.accounts.pool.paused, StakingError::Paused);
 
     let now = Clock::get()?.unix_timestamp;
-    let user_ata = &ctx.accounts.user_reward_ata;
     let pool = &mut ctx.accounts.pool;
     let user_stake = &mut ctx.accounts.user_stake;
-    let reward_mint = &ctx.accounts.reward_mint;
 
     // Sync the reward states
     sync_reward_vars(pool, now)?;
 
     // Calculate the reward pending to be claimed
-    let pending_reward = user_pending_reward(&user_stake, &pool)?;
+    let mut pending_reward = user_pending_reward(user_stake, pool)?;
+
+    // Settle the other positions into the same payout
+    require!(ctx.remaining_accounts.is_empty() || pool.vesting_duration == 0i64, StakingError::InvalidPosition);
+    for account_info in ctx.remaining_accounts.iter() {
+        let mut position: Account<'info, UserStake> = Account::try_from(account_info)?;
+        require!(position.key() != user_stake.key(), StakingError::InvalidPosition);
+        require!(position.pool == pool.key(), StakingError::InvalidPool);
+        require!(position.position_mint == Pubkey::default(), StakingError::PositionIsNft);
+        require!(
+            position.owner == ctx.accounts.user.key() || position.claim_delegate == ctx.accounts.user.key(),
+            StakingError::InvalidOwner
+        );
+        require!(reward_recipient_of(&position) == ctx.accounts.reward_recipient.key(), StakingError::InvalidOwner);
+        require!(position.referrer == user_stake.referrer, StakingError::InvalidReferrer);
+
+        let position_reward = user_pending_reward(&position, pool)?;
+        pending_reward = pending_reward.checked_add(position_reward).ok_or(StakingError::Overflow)?;
+
+        position.unclaimed_reward = 0u128;
+        update_user_weight(pool, &mut position, now)?;
+
+        // Persist the position, since it is not part of the validated accounts
+        position.exit(&crate::ID)?;
+    }
+
+    let pending_reward = take_reward_budget(pool, pending_reward)?;
     if pending_reward == 0u128 {
         return Ok(());
     }
 
-    // Converting to use for minting
-    let pending_reward_u64: u64 = pending_reward.try_into().map_err(|_| StakingError::Overflow)?;
+    let payout = RewardPayout {
+        cuts: RewardCuts {
+            pool,
+            reward_mint: &ctx.accounts.reward_mint,
+            referrer_account: ctx.accounts.referrer_account.as_mut(),
+            referrer_bump: ctx.bumps.referrer_account,
+            treasury_reward_ata: ctx.accounts.treasury_reward_ata.as_ref(),
+            token_program: &ctx.accounts.token_program,
+        },
+        position: user_stake.key(),
+        recipient: ctx.accounts.reward_recipient.key(),
+        recipient_ata: &ctx.accounts.user_reward_ata,
+        reward_vesting: ctx.accounts.reward_vesting.as_mut(),
+        reward_vesting_bump: ctx.bumps.reward_vesting,
+        vesting_vault: ctx.accounts.vesting_vault.as_ref(),
+    };
+    let (user_reward, fee_amount) = pay_reward(payout, user_stake, pending_reward, now)?;
+
+    // Update the reward debt
+    user_stake.unclaimed_reward = 0u128;
+    update_user_weight(pool, user_stake, now)?;
+
+    emit!(ClaimRewardEvent {
+        pool: pool.key(),
+        user: user_stake.owner,
+        recipient: ctx.accounts.reward_recipient.key(),
+        reward_claimed: user_reward,
+        fee_amount,
+    });
+    
+    Ok(())
+}
+
+/// Accounts splitting the referrer's cut and the protocol fee off a reward
+pub struct RewardCuts<'a, 'info> {
+    pub pool: &'a Account<'info, Pool>,
+    pub reward_mint: &'a InterfaceAccount<'info, Mint>,
+    pub referrer_account: Option<&'a mut Account<'info, ReferrerAccount>>,
+    pub referrer_bump: Option<u8>,
+    pub treasury_reward_ata: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+}
+
+/// Accounts paying a reward out to its recipient, directly or through the position's vesting schedule
+pub struct RewardPayout<'a, 'info> {
+    pub cuts: RewardCuts<'a, 'info>,
+    pub position: Pubkey,
+    pub recipient: Pubkey,
+    pub recipient_ata: &'a InterfaceAccount<'info, TokenAccount>,
+    pub reward_vesting: Option<&'a mut Account<'info, RewardVesting>>,
+    pub reward_vesting_bump: Option<u8>,
+    pub vesting_vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// @dev Accrues the referrer's cut of a reward taken from the budget and mints the protocol fee to the treasury
+/// @dev Returns the reward left for the position and the protocol fee
+pub fn take_reward_cuts(cuts: &mut RewardCuts, user_stake: &UserStake, reward: u128) -> Result<(u64, u64)> {
+    let pool = cuts.pool;
+    let reward_u64: u64 = reward.try_into().map_err(|_| StakingError::Overflow)?;
+
+    // Split the referrer's cut off the reward
+    let referral_reward: u64 = if user_stake.referrer != Pubkey::default() && pool.referral_bps > 0u16 {
+        let referrer_account = cuts.referrer_account.as_mut().ok_or(StakingError::MissingReferrerAccount)?;
+        if referrer_account.referrer == Pubkey::default() {
+            referrer_account.referrer = user_stake.referrer;
+            referrer_account.pool = pool.key();
+            referrer_account.bump = cuts.referrer_bump.ok_or(StakingError::MissingReferrerAccount)?;
+        }
+
+        let referral_reward = bps_of(reward, pool.referral_bps)?.try_into().map_err(|_| StakingError::Overflow)?;
+        referrer_account.accrued_reward = referrer_account.accrued_reward.checked_add(referral_reward).ok_or(StakingError::Overflow)?;
+        referrer_account.total_earned = referrer_account.total_earned.checked_add(referral_reward).ok_or(StakingError::Overflow)?;
+
+        emit!(ReferralRewardEvent {
+            pool: pool.key(),
+            referrer: user_stake.referrer,
+            referee: user_stake.owner,
+            amount: referral_reward,
+        });
+
+        referral_reward
+    } else {
+        0u64
+    };
+    let user_reward = reward_u64.checked_sub(referral_reward).ok_or(StakingError::Overflow)?;
+
+    // Split the protocol fee off the reward and send it to the treasury
+    let fee_amount: u64 = bps_of(reward, pool.fee_bps)?.try_into().map_err(|_| StakingError::Overflow)?;
+    let user_reward = user_reward.checked_sub(fee_amount).ok_or(StakingError::Overflow)?;
+
+    if fee_amount > 0u64 {
+        let treasury_reward_ata = cuts.treasury_reward_ata.ok_or(StakingError::MissingTreasuryAccount)?;
+
+        // Seeds that will be used for signing the transaction
+        let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+        let cpi_accounts = MintTo {
+            mint: cuts.reward_mint.to_account_info(),
+            to: treasury_reward_ata.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+
+        let cpi_program = cuts.token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+        token_interface::mint_to(cpi_context, fee_amount)?;
+    }
+
+    Ok((user_reward, fee_amount))
+}
+
+/// @dev Pays a reward taken from the budget -- the referrer's cut and the protocol fee are split off, and the rest
+/// is minted to the recipient, or locked in the position's vesting schedule in vesting pools
+/// @dev The position's rewards vest to one recipient at a time, recorded on the position
+/// @dev Returns the reward paid to the recipient and the protocol fee
+pub fn pay_reward(mut payout: RewardPayout, user_stake: &mut UserStake, reward: u128, now: i64) -> Result<(u64, u64)> {
+    if reward == 0u128 {
+        return Ok((0u64, 0u64));
+    }
+
+    let (user_reward, fee_amount) = take_reward_cuts(&mut payout.cuts, user_stake, reward)?;
+    let pool = payout.cuts.pool;
+
+    // Vesting pools mint into the vesting vault instead of the recipient's account
+    let vesting = if pool.vesting_duration > 0i64 {
+        check_vesting_recipient(user_stake, &payout.recipient, now)?;
+
+        match (payout.reward_vesting, payout.vesting_vault) {
+            (Some(reward_vesting), Some(vesting_vault)) => Some((reward_vesting, vesting_vault)),
+            _ => return err!(StakingError::MissingVestingAccount),
+        }
+    } else {
+        None
+    };
+
+    let reward_destination = match &vesting {
+        Some((_, vesting_vault)) => vesting_vault.to_account_info(),
+        None => payout.recipient_ata.to_account_info(),
+    };
 
     // Seeds that will be used for signing the transaction
-    let binding = ctx.accounts.stake_mint.key();
-    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), binding.as_ref(), &[ctx.bumps.pool]]];
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
 
     // Prepare and call the mint function
     let cpi_accounts = MintTo {
-        mint: reward_mint.to_account_info(),
-        to: user_ata.to_account_info(),
+        mint: payout.cuts.reward_mint.to_account_info(),
+        to: reward_destination,
         authority: pool.to_account_info(),
     };
 
-    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_program = payout.cuts.token_program.to_account_info();
 
     let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
-    token_interface::mint_to(cpi_context, pending_reward_u64)?;
+    token_interface::mint_to(cpi_context, user_reward)?;
 
-    // Update the reward debt
-    let prod = user_stake.shares.checked_mul(pool.acc_reward_per_share).ok_or(StakingError::Overflow)?;
-    let new_reward_debt = prod.checked_div(SCALING_FACTOR).ok_or(StakingError::Overflow)?;
-    user_stake.reward_debt = new_reward_debt;
+    // Lock the reward in the position's vesting schedule
+    if let Some((reward_vesting, _)) = vesting {
+        if reward_vesting.owner == Pubkey::default() {
+            reward_vesting.owner = payout.recipient;
+            reward_vesting.pool = pool.key();
+            reward_vesting.position = payout.position;
+            reward_vesting.bump = payout.reward_vesting_bump.ok_or(StakingError::MissingVestingAccount)?;
+        }
 
-    emit!(ClaimRewardEvent {
-        pool: pool.key(),
-        user: ctx.accounts.user.key(),
-        reward_claimed: pending_reward_u64,
-    });
-    
-    Ok(())
+        add_vesting_tranche(reward_vesting, user_reward, now, pool.vesting_duration, pool.vesting_cliff)?;
+
+        user_stake.vesting_recipient = payout.recipient;
+        user_stake.vesting_end = reward_vesting.end_time;
+
+        emit!(VestingTrancheEvent {
+            pool: pool.key(),
+            user: payout.recipient,
+            amount: user_reward,
+            cliff_time: reward_vesting.cliff_time,
+            end_time: reward_vesting.end_time,
+        });
+    }
+
+    Ok((user_reward, fee_amount))
 }
 
 //------------------------------------ ACCOUNTS ------------------------------------//
@@ -72,9 +256,20 @@ pub struct ClaimReward<'info> {
     )]
     pub pool: Account<'info, Pool>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_stake.pool == pool.key() @ StakingError::InvalidPool,
+        constraint = can_claim_position(&user_stake, &user.key(), position_nft.as_deref()) @ StakingError::InvalidOwner,
+    )]
     pub user_stake: Account<'info, UserStake>,
 
+    /// Required when the position is an NFT, the user's token account holding it
+    pub position_nft: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: receiver of the rewards, validated against the position
+    #[account(address = reward_recipient_for(&user_stake, &user.key()) @ StakingError::InvalidOwner)]
+    pub reward_recipient: UncheckedAccount<'info>,
+
     /// CHECK: stake mint
     pub stake_mint: UncheckedAccount<'info>,
 
@@ -92,11 +287,51 @@ pub struct ClaimReward<'info> {
         init_if_needed,
         payer = user,
         associated_token::mint = reward_mint,
-        associated_token::authority = user,
+        associated_token::authority = reward_recipient,
         associated_token::token_program = token_program,
     )]
     pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
 
+    /// Required when the pool vests claimed rewards
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardVesting::INIT_SPACE,
+        seeds = [
+            REWARD_VESTING_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user_stake.key().as_ref(),
+            reward_recipient.key().as_ref(),
+        ],
+        bump
+    )]
+    pub reward_vesting: Option<Account<'info, RewardVesting>>,
+
+    #[account(
+        mut,
+        seeds = [REWARD_VESTING_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub vesting_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the position has a referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ReferrerAccount::INIT_SPACE,
+        seeds = [REFERRER_SEED.as_bytes(), pool.key().as_ref(), user_stake.referrer.as_ref()],
+        bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// Required when the pool charges a protocol fee
+    #[account(
+        mut,
+        constraint = treasury_reward_ata.mint == pool.reward_mint,
+        constraint = treasury_reward_ata.owner == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub treasury_reward_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::states::{FEE_VAULT_SEED, POOL_SEED, Pool};
+use crate::utils::{CollectFeesEvent, StakingError};
+
+/// @dev Sends the collected deposit and withdrawal fees to the treasury -- ONLY ADMIN or fee recipient
+pub fn _collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+    let pool = &ctx.accounts.pool;
+    let fee_vault = &ctx.accounts.fee_vault;
+    let stake_mint = &ctx.accounts.stake_mint;
+
+    let amount = fee_vault.amount;
+    if amount == 0u64 {
+        return Ok(());
+    }
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Transfer from fee_vault --> treasury
+    let cpi_accounts = TransferChecked {
+        mint: stake_mint.to_account_info(),
+        from: fee_vault.to_account_info(),
+        to: ctx.accounts.treasury_stake_ata.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::transfer_checked(cpi_context, amount, stake_mint.decimals)?;
+
+    emit!(CollectFeesEvent {
+        pool: pool.key(),
+        collector: ctx.accounts.collector.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct CollectFees<'info> {
+    #[account(
+        constraint = collector.key() == pool.admin || collector.key() == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub collector: Signer<'info>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = stake_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_stake_ata.mint == pool.stake_mint,
+        constraint = treasury_stake_ata.owner == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub treasury_stake_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
+
+use crate::instructions::{take_reward_cuts, RewardCuts};
+use crate::states::{POOL_SEED, REFERRER_SEED, USER_STAKE_SEED, Pool, ReferrerAccount, UserStake};
+use crate::utils::{
+    CompoundEvent, StakingError, calculate_shares, check_deadline, check_pool_capacity, checkpoint_epoch,
+    sync_reward_vars, take_reward_budget, update_user_weight, user_pending_reward,
+};
+
+/// @dev Function to restake pending rewards into the pool -- ONLY when reward mint == stake mint
+/// @dev The referrer's cut and the protocol fee are taken like on a claim, vesting pools can not compound
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _compound(ctx: Context<Compound>, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+    require!(ctx.accounts.pool.reward_mint == ctx.accounts.pool.stake_mint, StakingError::MintMismatch);
+    require!(ctx.accounts.pool.vesting_duration == 0i64, StakingError::RewardsVest);
+
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let user_stake = &mut ctx.accounts.user_stake;
+    let stake_mint = &ctx.accounts.stake_mint;
+    let stake_vault = &ctx.accounts.stake_vault;
+
+    // Sync the reward states
+    sync_reward_vars(pool, now)?;
+
+    // Calculate the reward pending to be compounded
+    let pending_reward = user_pending_reward(user_stake, pool)?;
+    let pending_reward = take_reward_budget(pool, pending_reward)?;
+    if pending_reward == 0u128 {
+        return Ok(());
+    }
+
+    let mut cuts = RewardCuts {
+        pool,
+        reward_mint: stake_mint,
+        referrer_account: ctx.accounts.referrer_account.as_mut(),
+        referrer_bump: ctx.bumps.referrer_account,
+        treasury_reward_ata: ctx.accounts.treasury_reward_ata.as_ref(),
+        token_program: &ctx.accounts.token_program,
+    };
+    let (compounded, fee_amount) = take_reward_cuts(&mut cuts, user_stake, pending_reward)?;
+    let compounded_u128 = compounded as u128;
+
+    // Compounded rewards count against the pool capacity like a deposit, the per-user cap only bounds principal
+    let total_stake_after = pool.total_stake.checked_add(compounded_u128).ok_or(StakingError::Overflow)?;
+    check_pool_capacity(pool, total_stake_after)?;
+
+    // Issue shares at the current rate, before the reward is added to the pool
+    let shares = calculate_shares(pool, compounded_u128)?;
+
+    // Seeds that will be used for signing the transaction
+    let binding = stake_mint.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), binding.as_ref(), &[ctx.bumps.pool]]];
+
+    // Mint the reward straight into the stake vault
+    let cpi_accounts = MintTo {
+        mint: stake_mint.to_account_info(),
+        to: stake_vault.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::mint_to(cpi_context, compounded)?;
+
+    // Update pool
+    pool.total_stake = pool.total_stake.checked_add(compounded_u128).ok_or(StakingError::Overflow)?;
+    pool.total_shares = pool.total_shares.checked_add(shares).ok_or(StakingError::Overflow)?;
+
+    // Update user shares and reward debt
+    checkpoint_epoch(pool, user_stake, now)?;
+    user_stake.shares = user_stake.shares.checked_add(shares).ok_or(StakingError::Overflow)?;
+
+    user_stake.unclaimed_reward = 0u128;
+    update_user_weight(pool, user_stake, now)?;
+
+    emit!(CompoundEvent {
+        pool: pool.key(),
+        user: ctx.accounts.user.key(),
+        reward_compounded: compounded,
+        fee_amount,
+        shares_issued: shares,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct Compound<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(), stake_mint.key().as_ref()],
+        bump,
+        has_one = stake_vault,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    #[account(mut, constraint = stake_mint.key() == pool.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required when the position has a referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ReferrerAccount::INIT_SPACE,
+        seeds = [REFERRER_SEED.as_bytes(), pool.key().as_ref(), user_stake.referrer.as_ref()],
+        bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// Required when the pool charges a protocol fee
+    #[account(
+        mut,
+        constraint = treasury_reward_ata.mint == pool.reward_mint,
+        constraint = treasury_reward_ata.owner == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub treasury_reward_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,219 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
+
+use crate::instructions::{take_reward_cuts, RewardCuts};
+use crate::states::{POOL_SEED, REFERRER_SEED, Pool, ReferrerAccount, UserStake};
+use crate::utils::{
+    CrankCompoundEvent, StakingError, bps_of, calculate_shares, check_pool_capacity, checkpoint_epoch, epoch_ended,
+    has_unclaimed_epochs, sync_reward_vars, take_reward_budget, update_user_weight, user_pending_reward,
+};
+
+/// @dev Permissionless crank that compounds the rewards of opted-in positions
+/// @dev The `UserStake` accounts to compound are passed through `remaining_accounts`;
+/// positions that have not opted in, or whose max keeper fee is below the pool fee, are skipped
+/// @dev The referrer's cut and the protocol fee are taken before the keeper fee; in referral pools a position
+/// with a referrer is followed by its referrer's `ReferrerAccount`, and skipped while that is not created yet
+pub fn _crank_compound<'info>(ctx: Context<'_, '_, 'info, 'info, CrankCompound<'info>>) -> Result<()> {
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+    require!(ctx.accounts.pool.reward_mint == ctx.accounts.pool.stake_mint, StakingError::MintMismatch);
+    require!(ctx.accounts.pool.vesting_duration == 0i64, StakingError::RewardsVest);
+    require!(!ctx.remaining_accounts.is_empty(), StakingError::InvalidAmount);
+
+    let now = Clock::get()?.unix_timestamp;
+    let keeper = &ctx.accounts.keeper;
+    let pool = &mut ctx.accounts.pool;
+    let stake_mint = &ctx.accounts.stake_mint;
+
+    // Sync the reward states
+    sync_reward_vars(pool, now)?;
+
+    let keeper_fee_bps = pool.keeper_fee_bps;
+    let mut total_compounded = 0u128;
+    let mut total_keeper_fee = 0u128;
+
+    // Referrer accounts are loaded once, as several positions may share a referrer
+    let mut referrer_accounts: Vec<Account<'info, ReferrerAccount>> = Vec::new();
+
+    let mut accounts = ctx.remaining_accounts.iter();
+    while let Some(account_info) = accounts.next() {
+        let mut user_stake: Account<'info, UserStake> = Account::try_from(account_info)?;
+        require!(user_stake.pool == pool.key(), StakingError::InvalidPool);
+
+        // Positions with a referrer are followed by the referrer's account
+        let referrer_index = if user_stake.referrer != Pubkey::default() && pool.referral_bps > 0u16 {
+            let referrer_info = accounts.next().ok_or(StakingError::MissingReferrerAccount)?;
+            let (referrer_account_key, _) = Pubkey::find_program_address(
+                &[REFERRER_SEED.as_bytes(), pool.key().as_ref(), user_stake.referrer.as_ref()],
+                &crate::ID,
+            );
+            require!(referrer_info.key() == referrer_account_key, StakingError::MissingReferrerAccount);
+
+            // The referrer account is created by the position's next claim
+            if referrer_info.data_is_empty() {
+                continue;
+            }
+
+            match referrer_accounts.iter().position(|account| account.key() == referrer_account_key) {
+                Some(index) => Some(index),
+                None => {
+                    referrer_accounts.push(Account::try_from(referrer_info)?);
+                    Some(referrer_accounts.len() - 1)
+                }
+            }
+        } else {
+            None
+        };
+
+        if !user_stake.auto_compound || user_stake.max_keeper_fee_bps < keeper_fee_bps {
+            continue;
+        }
+
+        // Positions with finished epochs to claim, or in an ended epoch, can not change their shares yet
+        if has_unclaimed_epochs(pool, &user_stake) || epoch_ended(pool, now) {
+            continue;
+        }
+
+        let pending_reward = user_pending_reward(&user_stake, pool)?;
+
+        // Positions the whole reward could push past the pool capacity are left to compound themselves
+        let total_stake_after = pool.total_stake.checked_add(pending_reward).ok_or(StakingError::Overflow)?;
+        if check_pool_capacity(pool, total_stake_after).is_err() {
+            continue;
+        }
+
+        let pending_reward = take_reward_budget(pool, pending_reward)?;
+        if pending_reward == 0u128 {
+            continue;
+        }
+
+        let mut cuts = RewardCuts {
+            pool,
+            reward_mint: stake_mint,
+            referrer_account: referrer_index.map(|index| &mut referrer_accounts[index]),
+            referrer_bump: None,
+            treasury_reward_ata: ctx.accounts.treasury_reward_ata.as_ref(),
+            token_program: &ctx.accounts.token_program,
+        };
+        let (user_reward, fee_amount) = take_reward_cuts(&mut cuts, &user_stake, pending_reward)?;
+        let user_reward = user_reward as u128;
+
+        // Split the rest between the keeper and the position
+        let keeper_fee = bps_of(user_reward, keeper_fee_bps)?;
+        let compounded = user_reward.checked_sub(keeper_fee).ok_or(StakingError::Overflow)?;
+
+        // Issue shares at the current rate, before the reward is added to the pool
+        let shares = calculate_shares(pool, compounded)?;
+
+        pool.total_stake = pool.total_stake.checked_add(compounded).ok_or(StakingError::Overflow)?;
+        pool.total_shares = pool.total_shares.checked_add(shares).ok_or(StakingError::Overflow)?;
+
+        checkpoint_epoch(pool, &mut user_stake, now)?;
+        user_stake.shares = user_stake.shares.checked_add(shares).ok_or(StakingError::Overflow)?;
+
+        user_stake.unclaimed_reward = 0u128;
+        update_user_weight(pool, &mut user_stake, now)?;
+
+        // Persist the position, since it is not part of the validated accounts
+        user_stake.exit(&crate::ID)?;
+
+        total_compounded = total_compounded.checked_add(compounded).ok_or(StakingError::Overflow)?;
+        total_keeper_fee = total_keeper_fee.checked_add(keeper_fee).ok_or(StakingError::Overflow)?;
+
+        emit!(CrankCompoundEvent {
+            pool: pool.key(),
+            user: user_stake.owner,
+            keeper: keeper.key(),
+            reward_compounded: compounded.try_into().map_err(|_| StakingError::Overflow)?,
+            keeper_fee: keeper_fee.try_into().map_err(|_| StakingError::Overflow)?,
+            fee_amount,
+            shares_issued: shares,
+        });
+    }
+
+    // Persist the referrer accounts, since they are not part of the validated accounts
+    for referrer_account in referrer_accounts.iter() {
+        referrer_account.exit(&crate::ID)?;
+    }
+
+    // Seeds that will be used for signing the transaction
+    let binding = stake_mint.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), binding.as_ref(), &[ctx.bumps.pool]]];
+
+    // Mint the compounded rewards into the stake vault
+    if total_compounded > 0u128 {
+        let total_compounded_u64: u64 = total_compounded.try_into().map_err(|_| StakingError::Overflow)?;
+
+        let cpi_accounts = MintTo {
+            mint: stake_mint.to_account_info(),
+            to: ctx.accounts.stake_vault.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+        token_interface::mint_to(cpi_context, total_compounded_u64)?;
+    }
+
+    // Mint the keeper fees to the keeper
+    if total_keeper_fee > 0u128 {
+        let total_keeper_fee_u64: u64 = total_keeper_fee.try_into().map_err(|_| StakingError::Overflow)?;
+
+        let cpi_accounts = MintTo {
+            mint: stake_mint.to_account_info(),
+            to: ctx.accounts.keeper_reward_ata.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+        token_interface::mint_to(cpi_context, total_keeper_fee_u64)?;
+    }
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct CrankCompound<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(), stake_mint.key().as_ref()],
+        bump,
+        has_one = stake_vault,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut, constraint = stake_mint.key() == pool.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint = stake_mint,
+        associated_token::authority = keeper,
+        associated_token::token_program = token_program,
+    )]
+    pub keeper_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// Required when the pool charges a protocol fee
+    #[account(
+        mut,
+        constraint = treasury_reward_ata.mint == pool.reward_mint,
+        constraint = treasury_reward_ata.owner == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub treasury_reward_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
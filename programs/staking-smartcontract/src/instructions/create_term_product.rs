@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::states::{
+    FEE_VAULT_SEED, MAX_PRINCIPAL_FEE_BPS, MAX_TERM_APY_BPS, TERM_PRODUCT_SEED, TERM_REWARD_VAULT_SEED, TERM_VAULT_SEED, Pool,
+    TermProduct,
+};
+use crate::utils::{CreateTermProductEvent, StakingError};
+
+/// @dev Create a fixed-term deposit product, with its principal and reward vaults -- ONLY ADMIN
+/// @dev The pool's fee vault is created too if needed, as it receives the early redemption penalties
+/// @param `term_id` Id of the product within the pool
+/// @param `duration` Seconds from opening a deposit to its maturity
+/// @param `apy_bps` Simple interest over a year, prorated over the term, capped at `MAX_TERM_APY_BPS`
+/// @param `allow_early_redeem` Can deposits be redeemed before maturity, forfeiting the interest
+/// @param `early_redeem_penalty_bps` Share of the principal sent to the fee vault on early redemption
+pub fn _create_term_product(
+    ctx: Context<CreateTermProduct>,
+    term_id: u16,
+    duration: i64,
+    apy_bps: u32,
+    allow_early_redeem: bool,
+    early_redeem_penalty_bps: u16,
+) -> Result<()> {
+    require!(duration > 0i64, StakingError::InvalidTermProduct);
+    require!(apy_bps <= MAX_TERM_APY_BPS, StakingError::InvalidTermProduct);
+    require!(early_redeem_penalty_bps <= MAX_PRINCIPAL_FEE_BPS, StakingError::FeeTooHigh);
+
+    let term_product = &mut ctx.accounts.term_product;
+
+    term_product.pool = ctx.accounts.pool.key();
+    term_product.term_id = term_id;
+    term_product.duration = duration;
+    term_product.apy_bps = apy_bps;
+    term_product.allow_early_redeem = allow_early_redeem;
+    term_product.early_redeem_penalty_bps = early_redeem_penalty_bps;
+    term_product.total_principal = 0u64;
+    term_product.reserved_interest = 0u64;
+    term_product.position_count = 0u64;
+    term_product.bump = ctx.bumps.term_product;
+
+    emit!(CreateTermProductEvent {
+        pool: term_product.pool,
+        term_product: term_product.key(),
+        term_id,
+        duration,
+        apy_bps,
+        allow_early_redeem,
+        early_redeem_penalty_bps,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+#[instruction(term_id: u16)]
+pub struct CreateTermProduct<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(has_one = admin, has_one = stake_mint, has_one = reward_mint)]
+    pub pool: Account<'info, Pool>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + TermProduct::INIT_SPACE,
+        seeds = [TERM_PRODUCT_SEED.as_bytes(), pool.key().as_ref(), &term_id.to_le_bytes()],
+        bump
+    )]
+    pub term_product: Account<'info, TermProduct>,
+
+    #[account(
+        init,
+        payer = admin,
+        token::mint = stake_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [TERM_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump
+    )]
+    pub term_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = admin,
+        token::mint = reward_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [TERM_REWARD_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump
+    )]
+    pub term_reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = stake_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
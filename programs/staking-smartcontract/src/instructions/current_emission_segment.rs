@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{EmissionSegment, Pool};
+use crate::utils::emission_segment_at;
+
+/// @dev View returning the emission segment currently in effect
+pub fn _current_emission_segment(ctx: Context<CurrentEmissionSegment>) -> Result<EmissionSegment> {
+    let now = Clock::get()?.unix_timestamp;
+
+    Ok(emission_segment_at(&ctx.accounts.pool, now))
+}
+
+#[derive(Accounts)]
+pub struct CurrentEmissionSegment<'info> {
+    pub pool: Account<'info, Pool>,
+}
@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::states::Pool;
+use crate::utils::effective_apr_bps;
+
+/// @dev View returning the APR currently paid to stakers, in basis points
+pub fn _effective_apr(ctx: Context<EffectiveApr>) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+
+    effective_apr_bps(&ctx.accounts.pool, now)
+}
+
+#[derive(Accounts)]
+pub struct EffectiveApr<'info> {
+    pub pool: Account<'info, Pool>,
+}
@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
+
+use crate::states::{POOL_SEED, TERM_REWARD_VAULT_SEED, Pool, TermProduct};
+use crate::utils::{accrue_within_budget, take_reward_budget, FundTermRewardsEvent, StakingError};
+
+/// @dev Mint rewards into a term product's reward vault, to back the interest of new deposits -- ONLY ADMIN
+/// @dev The funding counts against the rewards budget
+/// @param `amount` Reward token to fund
+pub fn _fund_term_rewards(ctx: Context<FundTermRewards>, amount: u64) -> Result<()> {
+    require!(amount > 0u64, StakingError::InvalidAmount);
+
+    let pool = &mut ctx.accounts.pool;
+
+    // The whole funding must fit in the rewards budget
+    let funding = amount as u128;
+    require!(accrue_within_budget(pool, funding)? == funding, StakingError::InvalidRewardBudget);
+    require!(take_reward_budget(pool, funding)? == funding, StakingError::InvalidRewardBudget);
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.reward_mint.to_account_info(),
+        to: ctx.accounts.term_reward_vault.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::mint_to(cpi_context, amount)?;
+
+    emit!(FundTermRewardsEvent {
+        pool: pool.key(),
+        term_product: ctx.accounts.term_product.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FundTermRewards<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = admin,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(constraint = term_product.pool == pool.key() @ StakingError::InvalidPool)]
+    pub term_product: Account<'info, TermProduct>,
+
+    #[account(
+        mut,
+        seeds = [TERM_REWARD_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump,
+    )]
+    pub term_reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -2,27 +2,71 @@ use anchor_lang::prelude::*;
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 use crate::states::pool::*;
-use crate::utils::{InitializePoolEvent, StakingError};
+use crate::utils::{BPS_DENOMINATOR, InitializePoolEvent, StakingError};
 
 /// @notice Instruction to initialize the pool
 /// @params reward_rate Reward per second
+/// @params start_time Rewards accrue from this time, 0 or a past time starts the pool immediately
 pub fn _initialize_pool(
     ctx: Context<InitializePool>,
     reward_rate: u64,
+    start_time: i64,
 ) -> Result<()> {
     require!(reward_rate > 0u64, StakingError::InvalidAmount);
 
+    let now = Clock::get()?.unix_timestamp;
     let pool = &mut ctx.accounts.pool;
 
     pool.admin = ctx.accounts.admin.key();
     pool.stake_mint = ctx.accounts.stake_mint.key();
     pool.reward_mint = ctx.accounts.reward_mint.key();
     pool.stake_vault = ctx.accounts.stake_vault.key();
+    pool.stake_decimals = ctx.accounts.stake_mint.decimals;
+    pool.reward_decimals = ctx.accounts.reward_mint.decimals;
     pool.reward_rate = reward_rate;
     pool.total_stake = 0u128;
     pool.total_shares = 0u128;
+    pool.total_weighted_shares = 0u128;
     pool.acc_reward_per_share = 0u128;
-    pool.last_update_time = Clock::get()?.unix_timestamp;
+    pool.reward_remainder = 0u128;
+    pool.last_update_time = now;
+    pool.start_time = start_time.max(now);
+    pool.allow_pre_deposit = false;
+    pool.deposit_open = 0i64;
+    pool.deposit_close = 0i64;
+    pool.max_total_stake = 0u128;
+    pool.max_user_stake = 0u128;
+    pool.min_stake_amount = 0u64;
+    pool.emission_segments = [EmissionSegment::default(); MAX_EMISSION_SEGMENTS];
+    pool.emission_segment_count = 0u8;
+    pool.halving_interval = 0i64;
+    pool.halving_start = 0i64;
+    pool.max_total_rewards = 0u128;
+    pool.total_rewards_accrued = 0u128;
+    pool.total_rewards_paid = 0u128;
+    pool.reward_rate_mode = RewardRateMode::Fixed;
+    pool.reward_apr_bps = 0u32;
+    pool.price_ratio_num = 1u64;
+    pool.price_ratio_den = 1u64;
+    pool.rate_curve = RateCurve::default();
+    pool.epoch_duration = 0i64;
+    pool.current_epoch = 0u64;
+    pool.epoch_start = 0i64;
+    pool.epoch_eligible_shares = 0u128;
+    pool.keeper_fee_bps = 0u16;
+    pool.vesting_duration = 0i64;
+    pool.vesting_cliff = 0i64;
+    pool.forfeit_unvested_on_exit = false;
+    pool.max_lock_weeks = 0u16;
+    pool.max_boost_bps = BPS_DENOMINATOR as u16;
+    pool.loyalty_max_bps = BPS_DENOMINATOR as u16;
+    pool.loyalty_period = 0i64;
+    pool.loyalty_decay_bps = BPS_DENOMINATOR as u16;
+    pool.referral_bps = 0u16;
+    pool.fee_bps = 0u16;
+    pool.fee_recipient = ctx.accounts.admin.key();
+    pool.deposit_fee_bps = 0u16;
+    pool.withdraw_fee_bps = 0u16;
     pool.paused = false;
     pool.bump = ctx.bumps.pool;
 
@@ -30,6 +74,7 @@ pub fn _initialize_pool(
         pool: pool.key(),
         admin: ctx.accounts.admin.key(),
         reward_rate,
+        start_time: pool.start_time,
     });
 
     Ok(())
@@ -52,9 +97,9 @@ pub struct InitializePool<'info> {
     )]
     pub pool: Account<'info, Pool>,
 
-    /// CHECK: stake mint - validated
-    pub stake_mint: UncheckedAccount<'info>,
-    
+    #[account(mint::token_program = token_program)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
     #[account(mint::token_program = token_program)]
     pub reward_mint: InterfaceAccount<'info, Mint>,
 
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{Pool, UserStake};
+use crate::utils::{BPS_DENOMINATOR, KickEvent, StakingError, sync_reward_vars, update_user_weight, user_pending_reward};
+
+/// @dev Permissionless function to re-weight a position to its current lock boost and loyalty
+/// @dev Drops expired locks back to 1x and applies the loyalty earned since the last update
+pub fn _kick(ctx: Context<Kick>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    // Sync the reward states
+    sync_reward_vars(pool, now)?;
+
+    // Settle the rewards earned with the old weight before changing it
+    user_stake.unclaimed_reward = user_pending_reward(user_stake, pool)?;
+    if user_stake.lock_end <= now {
+        user_stake.boost_bps = BPS_DENOMINATOR as u16;
+    }
+
+    update_user_weight(pool, user_stake, now)?;
+
+    emit!(KickEvent {
+        pool: pool.key(),
+        user: user_stake.owner,
+        weighted_shares: user_stake.weighted_shares,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Kick<'info> {
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut, constraint = user_stake.pool == pool.key() @ StakingError::InvalidPool)]
+    pub user_stake: Account<'info, UserStake>,
+}
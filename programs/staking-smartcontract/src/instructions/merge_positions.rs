@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{USER_STAKE_SEED, Pool, UserStake};
+use crate::utils::{check_deadline, MergePositionsEvent, StakingError, move_shares, sync_reward_vars, user_pending_reward};
+
+/// @dev Function to merge a position into another position of the same user, closing it
+/// @dev The settled rewards of the closed position move along, and are paid to the remaining position's recipient.
+/// Both positions must share the referrer, so the referral cut keeps going to the same referrer
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _merge_positions(ctx: Context<MergePositions>, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+    require!(ctx.accounts.source_stake.key() != ctx.accounts.user_stake.key(), StakingError::InvalidPosition);
+    require!(ctx.accounts.source_stake.referrer == ctx.accounts.user_stake.referrer, StakingError::InvalidReferrer);
+
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let source_stake = &mut ctx.accounts.source_stake;
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    // Sync rewards before changing balances
+    sync_reward_vars(pool, now)?;
+
+    let shares = source_stake.shares;
+    if shares > 0u128 {
+        move_shares(pool, source_stake, user_stake, shares, now)?;
+    } else {
+        source_stake.unclaimed_reward = user_pending_reward(source_stake, pool)?;
+    }
+
+    // Carry over the settled rewards of the closed position
+    let unclaimed_reward = source_stake.unclaimed_reward;
+    user_stake.unclaimed_reward = user_stake.unclaimed_reward.checked_add(unclaimed_reward).ok_or(StakingError::Overflow)?;
+    source_stake.unclaimed_reward = 0u128;
+
+    emit!(MergePositionsEvent {
+        pool: pool.key(),
+        user: ctx.accounts.user.key(),
+        from_position: source_stake.position_index,
+        to_position: user_stake.position_index,
+        shares,
+        unclaimed_reward,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct MergePositions<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    /// The position merged away and closed
+    #[account(
+        mut,
+        close = user,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &source_stake.position_index.to_le_bytes(),
+        ],
+        bump = source_stake.bump,
+        constraint = source_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = source_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub source_stake: Account<'info, UserStake>,
+
+    /// The position receiving the shares
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{
+    self, spl_pod::optional_keys::OptionalNonZeroPubkey, spl_token_metadata_interface::state::TokenMetadata, Mint,
+    MintTo, TokenAccount, TokenMetadataInitialize,
+};
+
+use crate::states::{POOL_SEED, POSITION_NFT_NAME, POSITION_NFT_SYMBOL, POSITION_NFT_URI, Pool, UserStake};
+use crate::utils::{MintPositionNftEvent, StakingError};
+
+/// Accounts minting the NFT of a position as it is opened
+pub struct PositionNft<'a, 'info> {
+    pub payer: AccountInfo<'info>,
+    pub holder: Pubkey,
+    pub position_mint: &'a InterfaceAccount<'info, Mint>,
+    pub position_nft: &'a InterfaceAccount<'info, TokenAccount>,
+    pub position_token_program: &'a Program<'info, Token2022>,
+    pub system_program: &'a Program<'info, System>,
+}
+
+/// @dev Collects the optional NFT accounts of an instruction opening a position, none when the position is
+/// opened without an NFT
+pub fn position_nft_accounts<'a, 'info>(
+    payer: AccountInfo<'info>,
+    holder: Pubkey,
+    position_mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    position_nft: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    position_token_program: Option<&'a Program<'info, Token2022>>,
+    system_program: &'a Program<'info, System>,
+) -> Result<Option<PositionNft<'a, 'info>>> {
+    let Some(position_mint) = position_mint else {
+        return Ok(None);
+    };
+
+    Ok(Some(PositionNft {
+        payer,
+        holder,
+        position_mint,
+        position_nft: position_nft.ok_or(StakingError::MissingPositionNft)?,
+        position_token_program: position_token_program.ok_or(StakingError::MissingPositionNft)?,
+        system_program,
+    }))
+}
+
+/// @dev Represents a newly opened position as a 1-of-1 Token-2022 NFT, the pool being its mint and update authority
+/// @dev From then on the NFT holder controls the position, and rewards are paid to the holder. The reward recipient,
+/// claim delegate and auto-compounding of the position are cleared
+pub fn mint_position_nft<'info>(
+    nft: PositionNft<'_, 'info>,
+    pool: &Account<'info, Pool>,
+    user_stake: &mut Account<'info, UserStake>,
+) -> Result<()> {
+    require!(user_stake.shares > 0u128, StakingError::InvalidPosition);
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Fund the rent of the metadata, which the token program appends to the mint
+    let name = format!("{} #{}", POSITION_NFT_NAME, user_stake.position_index);
+    let metadata = TokenMetadata {
+        update_authority: OptionalNonZeroPubkey::try_from(Some(pool.key()))?,
+        mint: nft.position_mint.key(),
+        name: name.clone(),
+        symbol: POSITION_NFT_SYMBOL.to_string(),
+        uri: POSITION_NFT_URI.to_string(),
+        additional_metadata: vec![],
+    };
+    let mint_info = nft.position_mint.to_account_info();
+    let space = mint_info.data_len().checked_add(metadata.tlv_size_of()?).ok_or(StakingError::Overflow)?;
+    let rent_top_up = Rent::get()?.minimum_balance(space).saturating_sub(mint_info.lamports());
+
+    if rent_top_up > 0u64 {
+        let cpi_accounts = Transfer {
+            from: nft.payer,
+            to: mint_info.clone(),
+        };
+
+        let cpi_context = CpiContext::new(nft.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_context, rent_top_up)?;
+    }
+
+    // Write the metadata into the mint, the pool stays its update authority
+    let cpi_accounts = TokenMetadataInitialize {
+        program_id: nft.position_token_program.to_account_info(),
+        metadata: mint_info.clone(),
+        update_authority: pool.to_account_info(),
+        mint_authority: pool.to_account_info(),
+        mint: mint_info.clone(),
+    };
+
+    let cpi_program = nft.position_token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::token_metadata_initialize(
+        cpi_context,
+        name,
+        POSITION_NFT_SYMBOL.to_string(),
+        POSITION_NFT_URI.to_string(),
+    )?;
+
+    // Mint the only token to the holder
+    let cpi_accounts = MintTo {
+        mint: mint_info,
+        to: nft.position_nft.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = nft.position_token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::mint_to(cpi_context, 1u64)?;
+
+    // Hand the control of the position over to the NFT
+    user_stake.position_mint = nft.position_mint.key();
+    user_stake.reward_recipient = Pubkey::default();
+    user_stake.claim_delegate = Pubkey::default();
+    user_stake.auto_compound = false;
+
+    emit!(MintPositionNftEvent {
+        pool: pool.key(),
+        user: nft.holder,
+        user_stake: user_stake.key(),
+        position_mint: nft.position_mint.key(),
+    });
+
+    Ok(())
+}
@@ -14,4 +14,127 @@ pub mod set_pause;
 pub use set_pause::*;
 
 pub mod set_reward;
-pub use set_reward::*;
\ No newline at end of file
+pub use set_reward::*;
+
+pub mod compound;
+pub use compound::*;
+
+pub mod set_auto_compound;
+pub use set_auto_compound::*;
+
+pub mod set_keeper_fee;
+pub use set_keeper_fee::*;
+
+pub mod crank_compound;
+pub use crank_compound::*;
+
+pub mod set_vesting;
+pub use set_vesting::*;
+
+pub mod withdraw_vested;
+pub use withdraw_vested::*;
+
+pub mod set_lock_config;
+pub use set_lock_config::*;
+
+pub mod kick;
+pub use kick::*;
+
+pub mod set_loyalty_config;
+pub use set_loyalty_config::*;
+
+pub mod set_referral;
+pub use set_referral::*;
+
+pub mod claim_referral_reward;
+pub use claim_referral_reward::*;
+
+pub mod set_reward_recipient;
+pub use set_reward_recipient::*;
+
+pub mod set_claim_delegate;
+pub use set_claim_delegate::*;
+
+pub mod set_protocol_fee;
+pub use set_protocol_fee::*;
+
+pub mod set_principal_fees;
+pub use set_principal_fees::*;
+
+pub mod collect_fees;
+pub use collect_fees::*;
+
+pub mod set_emission_schedule;
+pub use set_emission_schedule::*;
+
+pub mod current_emission_segment;
+pub use current_emission_segment::*;
+
+pub mod set_reward_budget;
+pub use set_reward_budget::*;
+
+pub mod set_reward_apr;
+pub use set_reward_apr::*;
+
+pub mod effective_apr;
+pub use effective_apr::*;
+
+pub mod set_rate_curve;
+pub use set_rate_curve::*;
+
+pub mod set_epoch_mode;
+pub use set_epoch_mode::*;
+
+pub mod advance_epoch;
+pub use advance_epoch::*;
+
+pub mod claim_epoch;
+pub use claim_epoch::*;
+
+pub mod set_bonus_distribution;
+pub use set_bonus_distribution::*;
+
+pub mod claim_bonus;
+pub use claim_bonus::*;
+
+pub mod reclaim_bonus;
+pub use reclaim_bonus::*;
+
+pub mod set_start_time;
+pub use set_start_time::*;
+
+pub mod set_deposit_limits;
+pub use set_deposit_limits::*;
+
+pub mod create_term_product;
+pub use create_term_product::*;
+
+pub mod fund_term_rewards;
+pub use fund_term_rewards::*;
+
+pub mod open_term_deposit;
+pub use open_term_deposit::*;
+
+pub mod redeem_term;
+pub use redeem_term::*;
+
+pub mod transfer_position;
+pub use transfer_position::*;
+
+pub mod split_position;
+pub use split_position::*;
+
+pub mod merge_positions;
+pub use merge_positions::*;
+
+pub mod mint_position_nft;
+pub use mint_position_nft::*;
+
+pub mod stake_for;
+pub use stake_for::*;
+
+pub mod unstake_amount;
+pub use unstake_amount::*;
+
+pub mod unstake_all;
+pub use unstake_all::*;
\ No newline at end of file
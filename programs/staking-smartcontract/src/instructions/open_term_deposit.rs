@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::states::{
+    TERM_POSITION_SEED, TERM_PRODUCT_SEED, TERM_REWARD_VAULT_SEED, TERM_VAULT_SEED, Pool, TermPosition, TermProduct,
+};
+use crate::utils::{check_deadline, term_interest, OpenTermDepositEvent, StakingError};
+
+/// @dev Function to open a fixed-term deposit
+/// @dev The interest is fixed at opening and reserved out of the product's funded rewards
+/// @param `amount` The principal to deposit
+/// @param `term_id` Id of the term product
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _open_term_deposit(
+    ctx: Context<OpenTermDeposit>,
+    amount: u64,
+    term_id: u16,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+    require!(amount > 0u64, StakingError::InvalidAmount);
+
+    let now = Clock::get()?.unix_timestamp;
+    let user = &ctx.accounts.user;
+    let pool = &ctx.accounts.pool;
+    let term_product = &mut ctx.accounts.term_product;
+    let term_position = &mut ctx.accounts.term_position;
+    let stake_mint = &ctx.accounts.stake_mint;
+
+    let interest = term_interest(pool, amount, term_product.apy_bps, term_product.duration)?;
+    let maturity_time = now.checked_add(term_product.duration).ok_or(StakingError::Overflow)?;
+
+    // Reserve the interest, it must be covered by rewards no other deposit is owed
+    let reserved_interest = term_product.reserved_interest.checked_add(interest).ok_or(StakingError::Overflow)?;
+    require!(reserved_interest <= ctx.accounts.term_reward_vault.amount, StakingError::InsufficientTermRewards);
+
+    // Transfer from user --> term_vault
+    let cpi_accounts = TransferChecked {
+        mint: stake_mint.to_account_info(),
+        from: ctx.accounts.user_stake_ata.to_account_info(),
+        to: ctx.accounts.term_vault.to_account_info(),
+        authority: user.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
+
+    token_interface::transfer_checked(cpi_context, amount, stake_mint.decimals)?;
+
+    term_position.owner = user.key();
+    term_position.term_product = term_product.key();
+    term_position.position_id = term_product.position_count;
+    term_position.principal = amount;
+    term_position.interest = interest;
+    term_position.start_time = now;
+    term_position.maturity_time = maturity_time;
+    term_position.bump = ctx.bumps.term_position;
+
+    term_product.total_principal = term_product.total_principal.checked_add(amount).ok_or(StakingError::Overflow)?;
+    term_product.reserved_interest = reserved_interest;
+    term_product.position_count = term_product.position_count.checked_add(1u64).ok_or(StakingError::Overflow)?;
+
+    emit!(OpenTermDepositEvent {
+        pool: pool.key(),
+        term_product: term_product.key(),
+        term_position: term_position.key(),
+        term_id,
+        user: user.key(),
+        principal: amount,
+        interest,
+        maturity_time,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+#[instruction(amount: u64, term_id: u16)]
+pub struct OpenTermDeposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(has_one = stake_mint)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [TERM_PRODUCT_SEED.as_bytes(), pool.key().as_ref(), &term_id.to_le_bytes()],
+        bump = term_product.bump,
+    )]
+    pub term_product: Account<'info, TermProduct>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + TermPosition::INIT_SPACE,
+        seeds = [TERM_POSITION_SEED.as_bytes(), term_product.key().as_ref(), &term_product.position_count.to_le_bytes()],
+        bump
+    )]
+    pub term_position: Account<'info, TermPosition>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_stake_ata.mint == pool.stake_mint
+    )]
+    pub user_stake_ata: InterfaceAccount<'info, TokenAccount>, // user's token account for stake token
+
+    #[account(
+        mut,
+        seeds = [TERM_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump,
+    )]
+    pub term_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [TERM_REWARD_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump,
+    )]
+    pub term_reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
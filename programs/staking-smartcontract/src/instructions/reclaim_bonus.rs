@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Burn, Mint, TokenAccount, TokenInterface};
+
+use crate::states::{BONUS_DISTRIBUTION_SEED, BONUS_VAULT_SEED, POOL_SEED, BonusDistribution, Pool};
+use crate::utils::{ReclaimBonusEvent, StakingError};
+
+/// @dev Burn the bonuses left unclaimed after the campaign expired -- ONLY ADMIN
+/// @dev They are released back into the rewards budget
+pub fn _reclaim_bonus(ctx: Context<ReclaimBonus>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let bonus_vault = &ctx.accounts.bonus_vault;
+
+    require!(now >= ctx.accounts.bonus_distribution.expiry, StakingError::BonusNotExpired);
+
+    let amount = bonus_vault.amount;
+    if amount == 0u64 {
+        return Ok(());
+    }
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    let cpi_accounts = Burn {
+        mint: ctx.accounts.reward_mint.to_account_info(),
+        from: bonus_vault.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::burn(cpi_context, amount)?;
+
+    pool.total_rewards_accrued = pool.total_rewards_accrued.saturating_sub(amount as u128);
+    pool.total_rewards_paid = pool.total_rewards_paid.saturating_sub(amount as u128);
+
+    emit!(ReclaimBonusEvent {
+        pool: pool.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ReclaimBonus<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = admin,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [BONUS_DISTRIBUTION_SEED.as_bytes(), pool.key().as_ref()],
+        bump = bonus_distribution.bump,
+    )]
+    pub bonus_distribution: Account<'info, BonusDistribution>,
+
+    #[account(
+        mut,
+        seeds = [BONUS_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub bonus_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::states::{FEE_VAULT_SEED, POOL_SEED, TERM_REWARD_VAULT_SEED, TERM_VAULT_SEED, Pool, TermPosition, TermProduct};
+use crate::utils::{check_deadline, bps_of, RedeemTermEvent, StakingError};
+
+/// @dev Function to redeem a fixed-term deposit -- ONLY the owner
+/// @dev At or after maturity the principal and the interest are paid out. Products allowing early
+/// redemption return the principal before maturity, less the penalty, and forfeit the interest
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _redeem_term(ctx: Context<RedeemTerm>, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &ctx.accounts.pool;
+    let term_product = &mut ctx.accounts.term_product;
+    let term_position = &ctx.accounts.term_position;
+    let stake_mint = &ctx.accounts.stake_mint;
+    let reward_mint = &ctx.accounts.reward_mint;
+
+    let matured = now >= term_position.maturity_time;
+    require!(matured || term_product.allow_early_redeem, StakingError::TermNotMatured);
+
+    // Early redemptions forfeit the interest, which is released for other deposits, and pay a penalty
+    let (interest, penalty) = if matured {
+        (term_position.interest, 0u64)
+    } else {
+        let penalty = bps_of(term_position.principal as u128, term_product.early_redeem_penalty_bps)?;
+        (0u64, penalty.try_into().map_err(|_| StakingError::Overflow)?)
+    };
+    let principal = term_position.principal.checked_sub(penalty).ok_or(StakingError::Overflow)?;
+
+    term_product.total_principal = term_product.total_principal
+        .checked_sub(term_position.principal)
+        .ok_or(StakingError::Overflow)?;
+    term_product.reserved_interest = term_product.reserved_interest
+        .checked_sub(term_position.interest)
+        .ok_or(StakingError::Overflow)?;
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Transfer the principal from term_vault --> user
+    let cpi_accounts = TransferChecked {
+        mint: stake_mint.to_account_info(),
+        from: ctx.accounts.term_vault.to_account_info(),
+        to: ctx.accounts.user_stake_ata.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::transfer_checked(cpi_context, principal, stake_mint.decimals)?;
+
+    // Transfer the penalty from term_vault --> fee_vault
+    if penalty > 0u64 {
+        let cpi_accounts = TransferChecked {
+            mint: stake_mint.to_account_info(),
+            from: ctx.accounts.term_vault.to_account_info(),
+            to: ctx.accounts.fee_vault.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+        token_interface::transfer_checked(cpi_context, penalty, stake_mint.decimals)?;
+    }
+
+    // Transfer the interest from term_reward_vault --> user
+    if interest > 0u64 {
+        let cpi_accounts = TransferChecked {
+            mint: reward_mint.to_account_info(),
+            from: ctx.accounts.term_reward_vault.to_account_info(),
+            to: ctx.accounts.user_reward_ata.to_account_info(),
+            authority: pool.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+        token_interface::transfer_checked(cpi_context, interest, reward_mint.decimals)?;
+    }
+
+    emit!(RedeemTermEvent {
+        pool: pool.key(),
+        term_product: term_product.key(),
+        term_position: term_position.key(),
+        user: ctx.accounts.user.key(),
+        principal,
+        interest,
+        penalty,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct RedeemTerm<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = stake_mint,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = term_product.pool == pool.key() @ StakingError::InvalidPool,
+    )]
+    pub term_product: Account<'info, TermProduct>,
+
+    #[account(
+        mut,
+        close = user,
+        constraint = term_position.term_product == term_product.key() @ StakingError::InvalidTermProduct,
+        constraint = term_position.owner == user.key() @ StakingError::InvalidOwner,
+    )]
+    pub term_position: Account<'info, TermPosition>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_stake_ata.mint == pool.stake_mint
+    )]
+    pub user_stake_ata: InterfaceAccount<'info, TokenAccount>, // user's token account for stake token
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [TERM_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump,
+    )]
+    pub term_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [TERM_REWARD_VAULT_SEED.as_bytes(), term_product.key().as_ref()],
+        bump,
+    )]
+    pub term_reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Created with the term product, receives the early redemption penalty
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_KEEPER_FEE_BPS, USER_STAKE_SEED, Pool, UserStake};
+use crate::utils::{check_deadline, SetAutoCompoundEvent, StakingError};
+
+/// @dev Opts the user's position in or out of keeper auto-compounding
+/// @param `max_keeper_fee_bps` Highest keeper fee the user accepts per compound
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _set_auto_compound(
+    ctx: Context<SetAutoCompound>,
+    auto_compound: bool,
+    max_keeper_fee_bps: u16,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(max_keeper_fee_bps <= MAX_KEEPER_FEE_BPS, StakingError::FeeTooHigh);
+
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    user_stake.auto_compound = auto_compound;
+    user_stake.max_keeper_fee_bps = max_keeper_fee_bps;
+
+    emit!(SetAutoCompoundEvent {
+        pool: ctx.accounts.pool.key(),
+        user: ctx.accounts.user.key(),
+        auto_compound,
+        max_keeper_fee_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetAutoCompound<'info> {
+    pub user: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
@@ -0,0 +1,110 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface};
+
+use crate::states::{BONUS_DISTRIBUTION_SEED, BONUS_VAULT_SEED, MAX_BONUS_CLAIMS, POOL_SEED, BonusDistribution, Pool};
+use crate::utils::{accrue_within_budget, take_reward_budget, SetBonusDistributionEvent, StakingError};
+
+/// @dev Start a merkle-distributed bonus campaign, funding its vault with `total_amount` -- ONLY ADMIN
+/// @dev The previous campaign must have expired and been reclaimed. The bonus counts against the rewards budget
+/// @param `merkle_root` Root of the tree of `(index, claimant, amount)` leaves, built off-chain
+/// @param `total_amount` Sum of all bonuses in the tree
+/// @param `expiry` Bonuses can be claimed until this time
+pub fn _set_bonus_distribution(
+    ctx: Context<SetBonusDistribution>,
+    merkle_root: [u8; 32],
+    total_amount: u64,
+    expiry: i64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let bonus_distribution = &mut ctx.accounts.bonus_distribution;
+    let bonus_vault = &ctx.accounts.bonus_vault;
+
+    require!(total_amount > 0u64, StakingError::InvalidAmount);
+    require!(expiry > now, StakingError::BonusExpired);
+
+    // Only one campaign runs at a time
+    if bonus_distribution.pool != Pubkey::default() {
+        require!(now >= bonus_distribution.expiry && bonus_vault.amount == 0u64, StakingError::BonusActive);
+    }
+
+    // The whole bonus must fit in the rewards budget
+    let bonus = total_amount as u128;
+    require!(accrue_within_budget(pool, bonus)? == bonus, StakingError::InvalidRewardBudget);
+    require!(take_reward_budget(pool, bonus)? == bonus, StakingError::InvalidRewardBudget);
+
+    bonus_distribution.pool = pool.key();
+    bonus_distribution.merkle_root = merkle_root;
+    bonus_distribution.expiry = expiry;
+    bonus_distribution.total_amount = total_amount;
+    bonus_distribution.claimed_amount = 0u64;
+    bonus_distribution.claimed_bitmap = [0u8; MAX_BONUS_CLAIMS / 8];
+    bonus_distribution.bump = ctx.bumps.bonus_distribution;
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Mint the bonuses into the bonus vault
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.reward_mint.to_account_info(),
+        to: bonus_vault.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::mint_to(cpi_context, total_amount)?;
+
+    emit!(SetBonusDistributionEvent {
+        pool: pool.key(),
+        merkle_root,
+        total_amount,
+        expiry,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct SetBonusDistribution<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = admin,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + BonusDistribution::INIT_SPACE,
+        seeds = [BONUS_DISTRIBUTION_SEED.as_bytes(), pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_distribution: Account<'info, BonusDistribution>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = reward_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [BONUS_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump
+    )]
+    pub bonus_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{USER_STAKE_SEED, Pool, UserStake};
+use crate::utils::{check_deadline, SetClaimDelegateEvent, StakingError};
+
+/// @dev Lets `claim_delegate` claim rewards on the user's behalf -- it can never unstake
+/// @param `claim_delegate` The delegate, the default pubkey removes it
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _set_claim_delegate(
+    ctx: Context<SetClaimDelegate>,
+    claim_delegate: Pubkey,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    user_stake.claim_delegate = claim_delegate;
+
+    emit!(SetClaimDelegateEvent {
+        pool: ctx.accounts.pool.key(),
+        user: ctx.accounts.user.key(),
+        claim_delegate,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetClaimDelegate<'info> {
+    pub user: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+
+use crate::states::Pool;
+use crate::utils::{SetDepositLimitsEvent, StakingError};
+
+/// @dev Set the deposit window and capacity limits of the pool -- ONLY ADMIN
+/// @dev The limits only apply to new deposits, positions already above them are kept
+/// @param `deposit_open` `deposit_close` Deposits are accepted in `[open, close)`, 0 leaves a side unbounded
+/// @param `max_total_stake` Cap on the pool's total stake, 0 is unlimited
+/// @param `max_user_stake` Cap on the principal a wallet holds across all its positions, 0 is unlimited
+/// @param `min_stake_amount` Smallest amount a single deposit may stake
+pub fn _set_deposit_limits(
+    ctx: Context<SetDepositLimits>,
+    deposit_open: i64,
+    deposit_close: i64,
+    max_total_stake: u128,
+    max_user_stake: u128,
+    min_stake_amount: u64,
+) -> Result<()> {
+    require!(deposit_open >= 0i64 && deposit_close >= 0i64, StakingError::InvalidDepositWindow);
+    require!(deposit_close == 0i64 || deposit_close > deposit_open, StakingError::InvalidDepositWindow);
+
+    let pool = &mut ctx.accounts.pool;
+
+    pool.deposit_open = deposit_open;
+    pool.deposit_close = deposit_close;
+    pool.max_total_stake = max_total_stake;
+    pool.max_user_stake = max_user_stake;
+    pool.min_stake_amount = min_stake_amount;
+
+    emit!(SetDepositLimitsEvent {
+        pool: pool.key(),
+        deposit_open,
+        deposit_close,
+        max_total_stake,
+        max_user_stake,
+        min_stake_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetDepositLimits<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
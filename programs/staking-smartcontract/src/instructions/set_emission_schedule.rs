@@ -0,0 +1,53 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{EmissionSegment, MAX_EMISSION_SEGMENTS, Pool, RewardRateMode};
+use crate::utils::{sync_reward_vars, SetEmissionScheduleEvent, StakingError};
+
+/// @dev Set the emission schedule of the pool -- ONLY ADMIN
+/// @param `segments` Piecewise `(start_time, rate)` schedule sorted by start time, empty emits `reward_rate`
+/// @param `halving_interval` Seconds between halvings of `reward_rate` from now, 0 disables halving.
+/// Can not be combined with segments
+pub fn _set_emission_schedule(
+    ctx: Context<SetEmissionSchedule>,
+    segments: Vec<EmissionSegment>,
+    halving_interval: i64,
+) -> Result<()> {
+    require!(segments.len() <= MAX_EMISSION_SEGMENTS, StakingError::InvalidEmissionSchedule);
+    require!(halving_interval >= 0i64, StakingError::InvalidEmissionSchedule);
+    require!(segments.is_empty() || halving_interval == 0i64, StakingError::InvalidEmissionSchedule);
+    require!(
+        segments.windows(2).all(|pair| pair[0].start_time < pair[1].start_time),
+        StakingError::InvalidEmissionSchedule
+    );
+
+    let pool = &mut ctx.accounts.pool;
+    require!(pool.reward_rate_mode == RewardRateMode::Fixed, StakingError::InvalidEmissionSchedule);
+
+    // Sync the reward state before updating
+    let now = Clock::get()?.unix_timestamp;
+    sync_reward_vars(pool, now)?;
+
+    let mut emission_segments = [EmissionSegment::default(); MAX_EMISSION_SEGMENTS];
+    emission_segments[..segments.len()].copy_from_slice(&segments);
+
+    pool.emission_segments = emission_segments;
+    pool.emission_segment_count = segments.len() as u8;
+    pool.halving_interval = halving_interval;
+    pool.halving_start = now;
+
+    emit!(SetEmissionScheduleEvent {
+        pool: pool.key(),
+        segment_count: pool.emission_segment_count,
+        halving_interval,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetEmissionSchedule<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::states::Pool;
+use crate::utils::{sync_reward_vars, SetEpochModeEvent, StakingError};
+
+/// @dev Switch the pool to distribute rewards per epoch -- ONLY ADMIN
+/// @dev Continuous accrual stops for good, rewards accrued so far stay claimable through `claim_reward`.
+/// Once enabled only the duration can be updated, which also moves the end of the epoch in progress
+/// @param `epoch_duration` Length of an epoch in seconds
+pub fn _set_epoch_mode(ctx: Context<SetEpochMode>, epoch_duration: i64) -> Result<()> {
+    require!(epoch_duration > 0i64, StakingError::InvalidEpoch);
+
+    let pool = &mut ctx.accounts.pool;
+
+    if pool.epoch_duration == 0i64 {
+        // Settle the continuous accrual before it stops
+        let now = Clock::get()?.unix_timestamp;
+        sync_reward_vars(pool, now)?;
+
+        // Every current share is held for the whole first epoch unless unstaked
+        pool.current_epoch = 1u64;
+        pool.epoch_start = now.max(pool.start_time);
+        pool.epoch_eligible_shares = pool.total_shares;
+    }
+
+    pool.epoch_duration = epoch_duration;
+
+    emit!(SetEpochModeEvent {
+        pool: pool.key(),
+        epoch_duration,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetEpochMode<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
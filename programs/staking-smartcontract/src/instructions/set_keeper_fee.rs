@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_KEEPER_FEE_BPS, Pool};
+use crate::utils::{SetKeeperFeeEvent, StakingError};
+
+/// @dev Set the keeper fee taken from auto-compounded rewards -- ONLY ADMIN
+pub fn _set_keeper_fee(ctx: Context<SetKeeperFee>, keeper_fee_bps: u16) -> Result<()> {
+    require!(keeper_fee_bps <= MAX_KEEPER_FEE_BPS, StakingError::FeeTooHigh);
+
+    let pool = &mut ctx.accounts.pool;
+
+    pool.keeper_fee_bps = keeper_fee_bps;
+
+    emit!(SetKeeperFeeEvent {
+        pool: pool.key(),
+        keeper_fee_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetKeeperFee<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_BOOST_BPS, MAX_LOCK_WEEKS, Pool};
+use crate::utils::{BPS_DENOMINATOR, SetLockConfigEvent, StakingError, sync_reward_vars};
+
+/// @dev Set the lock and boost limits of the pool -- ONLY ADMIN
+/// @param `max_lock_weeks` Longest lock a staker can choose, 0 disables locking
+/// @param `max_boost_bps` Reward boost for a lock of `max_lock_weeks`, in basis points
+pub fn _set_lock_config(ctx: Context<SetLockConfig>, max_lock_weeks: u16, max_boost_bps: u16) -> Result<()> {
+    require!(max_lock_weeks <= MAX_LOCK_WEEKS, StakingError::InvalidLockDuration);
+    require!(
+        max_boost_bps as u128 >= BPS_DENOMINATOR && max_boost_bps <= MAX_BOOST_BPS,
+        StakingError::InvalidBoost
+    );
+
+    let pool = &mut ctx.accounts.pool;
+
+    // Sync the reward state before updating
+    let now = Clock::get()?.unix_timestamp;
+    sync_reward_vars(pool, now)?;
+
+    pool.max_lock_weeks = max_lock_weeks;
+    pool.max_boost_bps = max_boost_bps;
+
+    emit!(SetLockConfigEvent {
+        pool: pool.key(),
+        max_lock_weeks,
+        max_boost_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetLockConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_LOYALTY_BPS, Pool};
+use crate::utils::{BPS_DENOMINATOR, SetLoyaltyConfigEvent, StakingError, sync_reward_vars};
+
+/// @dev Set the loyalty multiplier of the pool -- ONLY ADMIN
+/// @dev Positions pick up the new multiplier the next time they are re-weighted
+/// @param `loyalty_max_bps` Multiplier reached after `loyalty_period`, 1x disables loyalty
+/// @param `loyalty_period` Seconds of uninterrupted staking to reach the max multiplier
+/// @param `loyalty_decay_bps` Share of the accrued loyalty time lost on unstake, 100% resets it
+pub fn _set_loyalty_config(
+    ctx: Context<SetLoyaltyConfig>,
+    loyalty_max_bps: u16,
+    loyalty_period: i64,
+    loyalty_decay_bps: u16,
+) -> Result<()> {
+    require!(
+        loyalty_max_bps as u128 >= BPS_DENOMINATOR && loyalty_max_bps <= MAX_LOYALTY_BPS,
+        StakingError::InvalidLoyaltyConfig
+    );
+    require!(loyalty_period > 0i64 || loyalty_max_bps as u128 == BPS_DENOMINATOR, StakingError::InvalidLoyaltyConfig);
+    require!(loyalty_decay_bps as u128 <= BPS_DENOMINATOR, StakingError::InvalidLoyaltyConfig);
+
+    let pool = &mut ctx.accounts.pool;
+
+    // Sync the reward state before updating
+    let now = Clock::get()?.unix_timestamp;
+    sync_reward_vars(pool, now)?;
+
+    pool.loyalty_max_bps = loyalty_max_bps;
+    pool.loyalty_period = loyalty_period;
+    pool.loyalty_decay_bps = loyalty_decay_bps;
+
+    emit!(SetLoyaltyConfigEvent {
+        pool: pool.key(),
+        loyalty_max_bps,
+        loyalty_period,
+        loyalty_decay_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetLoyaltyConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
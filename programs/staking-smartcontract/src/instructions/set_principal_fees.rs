@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::states::{FEE_VAULT_SEED, MAX_PRINCIPAL_FEE_BPS, Pool};
+use crate::utils::{SetPrincipalFeesEvent, StakingError};
+
+/// @dev Set the deposit and withdrawal fees of the pool -- ONLY ADMIN
+/// @dev Creates the fee vault the fees are collected in on first use
+pub fn _set_principal_fees(ctx: Context<SetPrincipalFees>, deposit_fee_bps: u16, withdraw_fee_bps: u16) -> Result<()> {
+    require!(
+        deposit_fee_bps <= MAX_PRINCIPAL_FEE_BPS && withdraw_fee_bps <= MAX_PRINCIPAL_FEE_BPS,
+        StakingError::FeeTooHigh
+    );
+
+    let pool = &mut ctx.accounts.pool;
+
+    pool.deposit_fee_bps = deposit_fee_bps;
+    pool.withdraw_fee_bps = withdraw_fee_bps;
+
+    emit!(SetPrincipalFeesEvent {
+        pool: pool.key(),
+        deposit_fee_bps,
+        withdraw_fee_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetPrincipalFees<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin, has_one = stake_mint)]
+    pub pool: Account<'info, Pool>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = stake_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
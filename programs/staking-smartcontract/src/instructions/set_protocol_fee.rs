@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_PROTOCOL_FEE_BPS, Pool};
+use crate::utils::{SetProtocolFeeEvent, StakingError};
+
+/// @dev Set the protocol fee taken from reward payouts -- ONLY ADMIN
+/// @param `fee_bps` Fee in basis points, capped at `MAX_PROTOCOL_FEE_BPS`
+/// @param `fee_recipient` Treasury wallet receiving the fee
+pub fn _set_protocol_fee(ctx: Context<SetProtocolFee>, fee_bps: u16, fee_recipient: Pubkey) -> Result<()> {
+    require!(fee_bps <= MAX_PROTOCOL_FEE_BPS, StakingError::FeeTooHigh);
+    require!(fee_recipient != Pubkey::default(), StakingError::InvalidOwner);
+
+    let pool = &mut ctx.accounts.pool;
+
+    pool.fee_bps = fee_bps;
+    pool.fee_recipient = fee_recipient;
+
+    emit!(SetProtocolFeeEvent {
+        pool: pool.key(),
+        fee_bps,
+        fee_recipient,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetProtocolFee<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
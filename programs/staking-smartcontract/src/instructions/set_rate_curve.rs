@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{Pool, RateCurve, RewardRateMode};
+use crate::utils::{sync_reward_vars, SetRateCurveEvent, StakingError, BPS_DENOMINATOR};
+
+/// @dev Switch the pool to a reward rate that follows its utilization -- ONLY ADMIN
+/// @param `rate_curve` Kinked curve from `base_rate` at no stake to `target_rate` at `target_stake`.
+/// The kink must lie strictly between 0% and 100% utilization
+pub fn _set_rate_curve(ctx: Context<SetRateCurve>, rate_curve: RateCurve) -> Result<()> {
+    require!(rate_curve.target_stake > 0u128, StakingError::InvalidRateCurve);
+    require!(
+        rate_curve.kink_bps > 0u16 && (rate_curve.kink_bps as u128) < BPS_DENOMINATOR,
+        StakingError::InvalidRateCurve
+    );
+
+    let pool = &mut ctx.accounts.pool;
+
+    // An emission schedule only applies to a fixed rate
+    require!(
+        pool.emission_segment_count == 0u8 && pool.halving_interval == 0i64,
+        StakingError::InvalidEmissionSchedule
+    );
+
+    // Sync the reward state before updating
+    sync_reward_vars(pool, Clock::get()?.unix_timestamp)?;
+
+    pool.reward_rate_mode = RewardRateMode::Utilization;
+    pool.rate_curve = rate_curve;
+
+    emit!(SetRateCurveEvent {
+        pool: pool.key(),
+        target_stake: rate_curve.target_stake,
+        base_rate: rate_curve.base_rate,
+        kink_bps: rate_curve.kink_bps,
+        kink_rate: rate_curve.kink_rate,
+        target_rate: rate_curve.target_rate,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRateCurve<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_REFERRAL_BPS, Pool};
+use crate::utils::{SetReferralEvent, StakingError};
+
+/// @dev Set the cut of a referee's rewards paid to their referrer -- ONLY ADMIN
+pub fn _set_referral(ctx: Context<SetReferral>, referral_bps: u16) -> Result<()> {
+    require!(referral_bps <= MAX_REFERRAL_BPS, StakingError::FeeTooHigh);
+
+    let pool = &mut ctx.accounts.pool;
+
+    pool.referral_bps = referral_bps;
+
+    emit!(SetReferralEvent {
+        pool: pool.key(),
+        referral_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetReferral<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
@@ -1,9 +1,11 @@
 use anchor_lang::prelude::*;
 
-use crate::states::Pool;
+use crate::states::{Pool, RewardRateMode};
 use crate::utils::{sync_reward_vars, SetRewardEvent};
 
 /// @dev Set the reward per rate value -- ONLY ADMIN
+/// @dev Switches an `Apr` or `Utilization` mode pool back to a fixed rate, the APR target and rate curve are kept
+/// but no longer applied
 pub fn _set_reward(ctx: Context<SetReward>, reward_rate: u64) -> Result<()> {
     let pool = &mut ctx.accounts.pool;
 
@@ -12,6 +14,7 @@ pub fn _set_reward(ctx: Context<SetReward>, reward_rate: u64) -> Result<()> {
     sync_reward_vars(pool, now)?;
 
     pool.reward_rate = reward_rate;
+    pool.reward_rate_mode = RewardRateMode::Fixed;
 
     // emit the event
     emit!(SetRewardEvent {
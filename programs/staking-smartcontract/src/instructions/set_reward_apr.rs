@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{MAX_REWARD_APR_BPS, Pool, RewardRateMode};
+use crate::utils::{sync_reward_vars, SetRewardAprEvent, StakingError};
+
+/// @dev Switch the pool to target an APR instead of a fixed rate -- ONLY ADMIN
+/// @dev The per-second emission follows `total_stake`, valued in reward token at the price ratio
+/// @param `reward_apr_bps` Targeted APR in basis points, capped at `MAX_REWARD_APR_BPS`
+/// @param `price_ratio_num` `price_ratio_den` One whole stake token is worth `num / den` whole reward tokens
+pub fn _set_reward_apr(
+    ctx: Context<SetRewardApr>,
+    reward_apr_bps: u32,
+    price_ratio_num: u64,
+    price_ratio_den: u64,
+) -> Result<()> {
+    require!(reward_apr_bps <= MAX_REWARD_APR_BPS, StakingError::InvalidRewardApr);
+    require!(price_ratio_num > 0u64 && price_ratio_den > 0u64, StakingError::InvalidRewardApr);
+
+    let pool = &mut ctx.accounts.pool;
+
+    // An emission schedule only applies to a fixed rate
+    require!(
+        pool.emission_segment_count == 0u8 && pool.halving_interval == 0i64,
+        StakingError::InvalidEmissionSchedule
+    );
+
+    // Sync the reward state before updating
+    sync_reward_vars(pool, Clock::get()?.unix_timestamp)?;
+
+    pool.reward_rate_mode = RewardRateMode::Apr;
+    pool.reward_apr_bps = reward_apr_bps;
+    pool.price_ratio_num = price_ratio_num;
+    pool.price_ratio_den = price_ratio_den;
+
+    emit!(SetRewardAprEvent {
+        pool: pool.key(),
+        reward_apr_bps,
+        price_ratio_num,
+        price_ratio_den,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRewardApr<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
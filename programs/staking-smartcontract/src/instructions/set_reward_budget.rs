@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::states::Pool;
+use crate::utils::{sync_reward_vars, SetRewardBudgetEvent, StakingError};
+
+/// @dev Set the hard cap on the rewards the pool will ever emit -- ONLY ADMIN
+/// @param `max_total_rewards` Budget in reward token, 0 is unlimited. Can not be below what has already accrued
+pub fn _set_reward_budget(ctx: Context<SetRewardBudget>, max_total_rewards: u128) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+
+    // Sync the reward state before updating
+    sync_reward_vars(pool, Clock::get()?.unix_timestamp)?;
+
+    require!(
+        max_total_rewards == 0u128 || max_total_rewards >= pool.total_rewards_accrued,
+        StakingError::InvalidRewardBudget
+    );
+
+    pool.max_total_rewards = max_total_rewards;
+
+    emit!(SetRewardBudgetEvent {
+        pool: pool.key(),
+        max_total_rewards,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRewardBudget<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
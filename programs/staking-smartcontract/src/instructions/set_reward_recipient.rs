@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{USER_STAKE_SEED, Pool, UserStake};
+use crate::utils::{check_deadline, check_vesting_recipient, reward_recipient_of, SetRewardRecipientEvent, StakingError};
+
+/// @dev Redirects the rewards of the user's position to `reward_recipient`
+/// @dev Rejected while the position's rewards are still vesting to the current recipient
+/// @param `reward_recipient` Receiver of the rewards, the default pubkey pays the owner again
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _set_reward_recipient(
+    ctx: Context<SetRewardRecipient>,
+    reward_recipient: Pubkey,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    user_stake.reward_recipient = reward_recipient;
+    check_vesting_recipient(user_stake, &reward_recipient_of(user_stake), now)?;
+
+    emit!(SetRewardRecipientEvent {
+        pool: ctx.accounts.pool.key(),
+        user: ctx.accounts.user.key(),
+        reward_recipient,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetRewardRecipient<'info> {
+    pub user: Signer<'info>,
+
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+}
@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::states::Pool;
+use crate::utils::{SetStartTimeEvent, StakingError};
+
+/// @dev Reschedule the start of the pool -- ONLY ADMIN, and only before it has started
+/// @param `start_time` Rewards accrue from this time, can not be in the past
+/// @param `allow_pre_deposit` Can users stake before the pool starts
+pub fn _set_start_time(ctx: Context<SetStartTime>, start_time: i64, allow_pre_deposit: bool) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+
+    require!(now < pool.start_time, StakingError::PoolAlreadyStarted);
+    require!(start_time >= now, StakingError::InvalidStartTime);
+
+    pool.start_time = start_time;
+    pool.allow_pre_deposit = allow_pre_deposit;
+
+    emit!(SetStartTimeEvent {
+        pool: pool.key(),
+        start_time,
+        allow_pre_deposit,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetStartTime<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin)]
+    pub pool: Account<'info, Pool>,
+}
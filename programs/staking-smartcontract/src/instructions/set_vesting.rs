@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::states::{REWARD_VESTING_VAULT_SEED, Pool};
+use crate::utils::{sync_reward_vars, SetVestingEvent, StakingError};
+
+/// @dev Configure vesting of claimed rewards -- ONLY ADMIN
+/// @param `vesting_duration` Seconds over which claims vest, 0 disables vesting
+/// @param `vesting_cliff` Seconds after a claim before any of it vests
+/// @param `forfeit_unvested_on_exit` Forfeit unvested rewards back to the pool when a user fully exits
+pub fn _set_vesting(
+    ctx: Context<SetVesting>,
+    vesting_duration: i64,
+    vesting_cliff: i64,
+    forfeit_unvested_on_exit: bool,
+) -> Result<()> {
+    require!(vesting_duration >= 0i64, StakingError::InvalidVestingSchedule);
+    require!(vesting_cliff >= 0i64 && vesting_cliff <= vesting_duration, StakingError::InvalidVestingSchedule);
+    require!(vesting_duration > 0i64 || !forfeit_unvested_on_exit, StakingError::InvalidVestingSchedule);
+
+    let pool = &mut ctx.accounts.pool;
+
+    // Sync the reward state before updating
+    let now = Clock::get()?.unix_timestamp;
+    sync_reward_vars(pool, now)?;
+
+    pool.vesting_duration = vesting_duration;
+    pool.vesting_cliff = vesting_cliff;
+    pool.forfeit_unvested_on_exit = forfeit_unvested_on_exit;
+
+    emit!(SetVestingEvent {
+        pool: pool.key(),
+        vesting_duration,
+        vesting_cliff,
+        forfeit_unvested_on_exit,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetVesting<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, has_one = admin, has_one = reward_mint)]
+    pub pool: Account<'info, Pool>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        token::mint = reward_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        seeds = [REWARD_VESTING_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,144 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::instructions::{mint_position_nft, position_nft_accounts};
+use crate::states::{POSITION_COUNTER_SEED, POSITION_MINT_SEED, USER_STAKE_SEED, Pool, PositionCounter, UserStake};
+use crate::utils::{check_deadline, SplitPositionEvent, StakingError, move_shares, open_position, sync_reward_vars};
+
+/// @dev Function to split shares off a position into a new position of the same user
+/// @dev The new position takes the user's next index and keeps the lock and loyalty of the source. It is opened
+/// as an NFT when the position NFT accounts are passed
+/// @param `shares` The shares to split off
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _split_position(ctx: Context<SplitPosition>, shares: u128, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let now = Clock::get()?.unix_timestamp;
+    let user = &ctx.accounts.user;
+    let pool = &mut ctx.accounts.pool;
+    let user_stake = &mut ctx.accounts.user_stake;
+    let new_stake = &mut ctx.accounts.new_stake;
+    let position_index = ctx.accounts.position_counter.position_count;
+    let counter_bump = ctx.accounts.position_counter.bump;
+
+    open_position(
+        &mut ctx.accounts.position_counter,
+        counter_bump,
+        new_stake,
+        ctx.bumps.new_stake,
+        user.key(),
+        pool.key(),
+        position_index,
+    )?;
+
+    // The split off shares are paid to the same recipient and referrer
+    new_stake.referrer = user_stake.referrer;
+    new_stake.reward_recipient = user_stake.reward_recipient;
+    new_stake.claim_delegate = user_stake.claim_delegate;
+
+    // Sync rewards before changing balances
+    sync_reward_vars(pool, now)?;
+
+    move_shares(pool, user_stake, new_stake, shares, now)?;
+
+    // Open the new position as an NFT when its accounts are passed
+    let position_nft = position_nft_accounts(
+        user.to_account_info(),
+        user.key(),
+        ctx.accounts.position_mint.as_deref(),
+        ctx.accounts.position_nft.as_deref(),
+        ctx.accounts.position_token_program.as_ref(),
+        &ctx.accounts.system_program,
+    )?;
+    if let Some(position_nft) = position_nft {
+        mint_position_nft(position_nft, pool, new_stake)?;
+    }
+
+    emit!(SplitPositionEvent {
+        pool: pool.key(),
+        user: user.key(),
+        from_position: user_stake.position_index,
+        to_position: position_index,
+        shares,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct SplitPosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    #[account(
+        mut,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(), pool.key().as_ref(), user.key().as_ref()],
+        bump = position_counter.bump,
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &position_counter.position_count.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub new_stake: Account<'info, UserStake>,
+
+    /// Required to open the position as an NFT, only when the position is new
+    #[account(
+        init,
+        payer = user,
+        seeds = [POSITION_MINT_SEED.as_bytes(), new_stake.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool,
+        mint::freeze_authority = pool,
+        mint::token_program = position_token_program,
+        extensions::metadata_pointer::authority = pool,
+        extensions::metadata_pointer::metadata_address = position_mint,
+    )]
+    pub position_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = position_mint,
+        associated_token::authority = user,
+        associated_token::token_program = position_token_program,
+    )]
+    pub position_nft: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub position_token_program: Option<Program<'info, Token2022>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    pub system_program: Program<'info, System>,
+}
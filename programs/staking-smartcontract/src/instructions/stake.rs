@@ -1,83 +1,235 @@
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::Token2022;
 use anchor_spl::token_interface::{self, Mint, TokenInterface, TokenAccount, TransferChecked};
 
-use crate::states::{Pool, UserStake, USER_STAKE_SEED};
-use crate::utils::{SCALING_FACTOR, StakeEvent, StakingError, sync_reward_vars};
+use crate::instructions::{mint_position_nft, position_nft_accounts};
+use crate::states::{
+    Pool, PositionCounter, UserStake, FEE_VAULT_SEED, POSITION_COUNTER_SEED, POSITION_MINT_SEED, SECONDS_PER_WEEK,
+    USER_STAKE_SEED,
+};
+use crate::utils::{
+    StakeEvent, StakingError, blended_stake_time, bps_of, calculate_boost, calculate_shares, check_deadline,
+    check_pool_capacity, check_referral_chain, check_user_capacity, checkpoint_epoch, open_position, sync_reward_vars,
+    update_user_weight, user_pending_reward,
+};
 
 /// @dev Function to add stakes into the pool
+/// @dev A new position is opened as an NFT when the position NFT accounts are passed
 /// @param `stake_amount` The amount to deposit
-pub fn _stake(ctx: Context<Stake>, stake_amount: u64) -> Result<()> {
-    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+/// @param `lock_weeks` Weeks to lock the whole position for, 0 keeps the current lock
+/// @param `referrer` Who referred the user, only recorded once per user and pool. The position counters of the
+/// referrer's own chain of referrers are passed in `remaining_accounts`, nearest first
+/// @param `position_index` Position to stake into, the user's position count opens a new one
+/// @param `min_shares_out` The least shares to receive, guarding against a moving share price
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _stake<'info>(
+    ctx: Context<'_, '_, 'info, 'info, Stake<'info>>,
+    stake_amount: u64,
+    lock_weeks: u16,
+    referrer: Option<Pubkey>,
+    position_index: u64,
+    min_shares_out: u128,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
 
     let now = Clock::get()?.unix_timestamp;
-
     let user = &ctx.accounts.user;
     let pool = &mut ctx.accounts.pool;
-    let user_stake_ata = &ctx.accounts.user_stake_ata;
-    let stake_mint = &ctx.accounts.stake_mint;
-    let stake_vault = &ctx.accounts.stake_vault;
     let user_stake = &mut ctx.accounts.user_stake;
-    
+
+    // Positions are opened in order, each new one takes the next index
+    let opened = user_stake.owner == Pubkey::default();
+    open_position(
+        &mut ctx.accounts.position_counter,
+        ctx.bumps.position_counter,
+        user_stake,
+        ctx.bumps.user_stake,
+        user.key(),
+        pool.key(),
+        position_index,
+    )?;
+
+    // Record the referrer once per user, later positions inherit it
+    let position_counter = &mut ctx.accounts.position_counter;
+    if let Some(referrer) = referrer {
+        if position_counter.referrer == Pubkey::default() {
+            require!(referrer != user.key() && referrer != Pubkey::default(), StakingError::InvalidReferrer);
+
+            // Reject loops -- the position counters of the referrer's chain of referrers follow `referrer_counter`
+            // in `remaining_accounts`, in order
+            let referrer_counter = ctx.accounts.referrer_counter.as_ref().ok_or(StakingError::InvalidReferrer)?;
+            let counters = std::iter::once(referrer_counter.as_ref()).chain(ctx.remaining_accounts.iter());
+            check_referral_chain(counters, referrer, &user.key(), &pool.key(), ctx.program_id)?;
+
+            position_counter.referrer = referrer;
+        }
+    }
+
+    // Positions opened before the referrer was recorded pick it up on their next stake
+    if user_stake.referrer == Pubkey::default() {
+        user_stake.referrer = position_counter.referrer;
+    }
+
+    let deposit_fee = stake_core(
+        StakeDeposit {
+            funder: user,
+            funder_ata: &ctx.accounts.user_stake_ata,
+            stake_mint: &ctx.accounts.stake_mint,
+            stake_vault: &ctx.accounts.stake_vault,
+            fee_vault: ctx.accounts.fee_vault.as_ref(),
+            token_program: &ctx.accounts.token_program,
+            position_counter,
+        },
+        pool,
+        user_stake,
+        stake_amount,
+        lock_weeks,
+        min_shares_out,
+        now,
+    )?;
+
+    // Open the position as an NFT when its accounts are passed
+    let position_nft = position_nft_accounts(
+        user.to_account_info(),
+        user.key(),
+        ctx.accounts.position_mint.as_deref(),
+        ctx.accounts.position_nft.as_deref(),
+        ctx.accounts.position_token_program.as_ref(),
+        &ctx.accounts.system_program,
+    )?;
+    if let Some(position_nft) = position_nft {
+        require!(opened, StakingError::InvalidPosition);
+        mint_position_nft(position_nft, pool, user_stake)?;
+    }
+
+    emit!(StakeEvent {
+        user: user.key(),
+        pool: pool.key(),
+        stake_amount,
+        deposit_fee,
+        lock_end: user_stake.lock_end,
+    });
+
+    Ok(())
+}
+
+/// Token accounts moving a deposit from the funder into the pool, and the counter of the owner it is credited to
+pub struct StakeDeposit<'a, 'info> {
+    pub funder: &'a Signer<'info>,
+    pub funder_ata: &'a InterfaceAccount<'info, TokenAccount>,
+    pub stake_mint: &'a InterfaceAccount<'info, Mint>,
+    pub stake_vault: &'a InterfaceAccount<'info, TokenAccount>,
+    pub fee_vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+    pub position_counter: &'a mut PositionCounter,
+}
+
+/// @dev Shared core of `stake` and `stake_for` -- deposits `stake_amount` from the funder into an opened position,
+/// adding it to the principal on the position owner's counter
+/// @dev Returns the deposit fee taken
+pub fn stake_core(
+    deposit: StakeDeposit,
+    pool: &mut Pool,
+    user_stake: &mut UserStake,
+    stake_amount: u64,
+    lock_weeks: u16,
+    min_shares_out: u128,
+    now: i64,
+) -> Result<u64> {
+    require!(!pool.paused, StakingError::Paused);
+    require!(lock_weeks <= pool.max_lock_weeks, StakingError::InvalidLockDuration);
+    require!(now >= pool.start_time || pool.allow_pre_deposit, StakingError::PoolNotStarted);
+    require!(stake_amount >= pool.min_stake_amount, StakingError::StakeBelowMinimum);
+    require!(now >= pool.deposit_open, StakingError::DepositsNotOpen);
+    require!(pool.deposit_close == 0i64 || now < pool.deposit_close, StakingError::DepositsClosed);
+
+    let stake_mint = deposit.stake_mint;
+
     // Sync rewards before changing balances
     sync_reward_vars(pool, now)?;
 
-    let stake_amount_u128: u128 = stake_amount as u128;
+    // Take the deposit fee before minting shares
+    let deposit_fee: u64 = bps_of(stake_amount as u128, pool.deposit_fee_bps)?.try_into().map_err(|_| StakingError::Overflow)?;
+    let net_amount = stake_amount.checked_sub(deposit_fee).ok_or(StakingError::Overflow)?;
 
-    let shares: u128 = if pool.total_shares == 0 || pool.total_stake == 0 {
-        stake_amount_u128
-    } else {
-        // shares = stake_amount * total_shares / total_stake
-        let prod = stake_amount_u128.checked_mul(pool.total_shares).ok_or(StakingError::Overflow)?;
-        prod.checked_div(pool.total_stake).ok_or(StakingError::Overflow)?
-    };
+    let stake_amount_u128: u128 = net_amount as u128;
+
+    let shares: u128 = calculate_shares(pool, stake_amount_u128)?;
+    require!(shares >= min_shares_out, StakingError::SlippageExceeded);
 
-    // Transfer from user --> stake_vault
+    // Enforce the pool capacity, and the per-user capacity across all the owner's positions
+    let total_stake_after = pool.total_stake.checked_add(stake_amount_u128).ok_or(StakingError::Overflow)?;
+    let total_principal_after = deposit.position_counter.total_principal.checked_add(stake_amount_u128).ok_or(StakingError::Overflow)?;
+    check_pool_capacity(pool, total_stake_after)?;
+    check_user_capacity(pool, total_principal_after)?;
+
+    // Transfer from funder --> stake_vault
     let cpi_accounts = TransferChecked {
         mint: stake_mint.to_account_info(),
-        from: user_stake_ata.to_account_info(),
-        to: stake_vault.to_account_info(),
-        authority: user.to_account_info(),
+        from: deposit.funder_ata.to_account_info(),
+        to: deposit.stake_vault.to_account_info(),
+        authority: deposit.funder.to_account_info(),
     };
 
-    let cpi_program = ctx.accounts.token_program.to_account_info();
+    let cpi_program = deposit.token_program.to_account_info();
     let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
 
-    token_interface::transfer_checked(cpi_context, stake_amount, stake_mint.decimals)?;
+    token_interface::transfer_checked(cpi_context, net_amount, stake_mint.decimals)?;
+
+    // Transfer the deposit fee from funder --> fee_vault
+    if deposit_fee > 0u64 {
+        let fee_vault = deposit.fee_vault.ok_or(StakingError::MissingFeeVault)?;
+
+        let cpi_accounts = TransferChecked {
+            mint: stake_mint.to_account_info(),
+            from: deposit.funder_ata.to_account_info(),
+            to: fee_vault.to_account_info(),
+            authority: deposit.funder.to_account_info(),
+        };
+
+        let cpi_program = deposit.token_program.to_account_info();
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
+
+        token_interface::transfer_checked(cpi_context, deposit_fee, stake_mint.decimals)?;
+    }
 
     // Update pool
     pool.total_stake = pool.total_stake.checked_add(stake_amount_u128).ok_or(StakingError::Overflow)?;
     pool.total_shares = pool.total_shares.checked_add(shares).ok_or(StakingError::Overflow)?;
+    deposit.position_counter.total_principal = total_principal_after;
 
-    // If new account, set owner/pool
-    if user_stake.owner == Pubkey::default() {
-        user_stake.owner = user.key();
-        user_stake.pool = pool.key();
-        user_stake.bump = ctx.bumps.user_stake;
-    } else {
-        require!(user_stake.owner == user.key(), StakingError::InvalidOwner);
-        require!(user_stake.pool == pool.key(), StakingError::InvalidPool);
-    }
+    // Settle the pending rewards before the weight changes
+    user_stake.unclaimed_reward = user_pending_reward(user_stake, pool)?;
+
+    // The new shares start their loyalty period now, a new position with them
+    user_stake.last_stake_time = blended_stake_time(pool, user_stake, shares, now)?;
+
+    // New shares only count towards the epochs after this one
+    checkpoint_epoch(pool, user_stake, now)?;
 
     // Update user shares
     user_stake.shares = user_stake.shares.checked_add(shares).ok_or(StakingError::Overflow)?;
 
-    // Set new reward_debt = user.shares * reward_per_share / SCALING
-    let prod = user_stake.shares.checked_mul(pool.acc_reward_per_share).ok_or(StakingError::Overflow)?;
-    user_stake.reward_debt = prod.checked_div(SCALING_FACTOR).ok_or(StakingError::Overflow)?;
+    // Extend the lock, and boost the whole position by the lock remaining
+    if lock_weeks > 0u16 {
+        let lock_duration = (lock_weeks as i64).checked_mul(SECONDS_PER_WEEK).ok_or(StakingError::Overflow)?;
+        let lock_end = now.checked_add(lock_duration).ok_or(StakingError::Overflow)?;
+        user_stake.lock_end = user_stake.lock_end.max(lock_end);
+    }
+    user_stake.boost_bps = calculate_boost(pool, user_stake.lock_end - now)?;
 
-    emit!(StakeEvent {
-        user: user.key(),
-        pool: pool.key(),
-        stake_amount,
-    });
+    update_user_weight(pool, user_stake, now)?;
 
-    Ok(())
+    Ok(deposit_fee)
 }
 
 
 //------------------------------------ Accounts ------------------------------------//
 
 #[derive(Accounts)]
+#[instruction(stake_amount: u64, lock_weeks: u16, referrer: Option<Pubkey>, position_index: u64)]
 pub struct Stake<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
@@ -101,11 +253,60 @@ pub struct Stake<'info> {
         init_if_needed,
         payer = user,
         space = 8 + UserStake::INIT_SPACE,
-        seeds = [USER_STAKE_SEED.as_bytes(), pool.key().as_ref(), user.key().as_ref()],
-        bump
+        seeds = [USER_STAKE_SEED.as_bytes(), pool.key().as_ref(), user.key().as_ref(), &position_index.to_le_bytes()],
+        bump,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
     )]
     pub user_stake: Account<'info, UserStake>,
 
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + PositionCounter::INIT_SPACE,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(), pool.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
+    /// Required when the pool charges a deposit fee
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the referrer's position counter in this pool, required when recording a referrer.
+    /// Its address is validated in the handler and it may not exist yet
+    pub referrer_counter: Option<UncheckedAccount<'info>>,
+
+    /// Required to open the position as an NFT, only when the position is new
+    #[account(
+        init,
+        payer = user,
+        seeds = [POSITION_MINT_SEED.as_bytes(), user_stake.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool,
+        mint::freeze_authority = pool,
+        mint::token_program = position_token_program,
+        extensions::metadata_pointer::authority = pool,
+        extensions::metadata_pointer::metadata_address = position_mint,
+    )]
+    pub position_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = position_mint,
+        associated_token::authority = user,
+        associated_token::token_program = position_token_program,
+    )]
+    pub position_nft: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub position_token_program: Option<Program<'info, Token2022>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
\ No newline at end of file
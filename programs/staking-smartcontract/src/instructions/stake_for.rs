@@ -0,0 +1,173 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::instructions::{mint_position_nft, position_nft_accounts, stake_core, StakeDeposit};
+use crate::states::{
+    FEE_VAULT_SEED, POSITION_COUNTER_SEED, POSITION_MINT_SEED, USER_STAKE_SEED, Pool, PositionCounter, UserStake,
+};
+use crate::utils::{check_deadline, StakeForEvent, StakingError, open_position};
+
+/// @dev Function to stake into another wallet's position -- the funder pays the tokens and the rent
+/// @dev The beneficiary owns the position, the funder can neither lock it nor set its referrer
+/// @dev A new position is opened as an NFT held by the beneficiary when the position NFT accounts are passed
+/// @param `beneficiary` The owner of the position
+/// @param `stake_amount` The amount to deposit
+/// @param `position_index` Beneficiary position to stake into, their position count opens a new one
+/// @param `min_shares_out` The least shares to issue, guarding against a moving share price
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _stake_for(
+    ctx: Context<StakeFor>,
+    beneficiary: Pubkey,
+    stake_amount: u64,
+    position_index: u64,
+    min_shares_out: u128,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(beneficiary != Pubkey::default(), StakingError::InvalidOwner);
+
+    let now = Clock::get()?.unix_timestamp;
+    let funder = &ctx.accounts.funder;
+    let pool = &mut ctx.accounts.pool;
+    let user_stake = &mut ctx.accounts.user_stake;
+
+    let opened = user_stake.owner == Pubkey::default();
+    open_position(
+        &mut ctx.accounts.position_counter,
+        ctx.bumps.position_counter,
+        user_stake,
+        ctx.bumps.user_stake,
+        beneficiary,
+        pool.key(),
+        position_index,
+    )?;
+
+    let deposit_fee = stake_core(
+        StakeDeposit {
+            funder,
+            funder_ata: &ctx.accounts.funder_stake_ata,
+            stake_mint: &ctx.accounts.stake_mint,
+            stake_vault: &ctx.accounts.stake_vault,
+            fee_vault: ctx.accounts.fee_vault.as_ref(),
+            token_program: &ctx.accounts.token_program,
+            position_counter: &mut ctx.accounts.position_counter,
+        },
+        pool,
+        user_stake,
+        stake_amount,
+        0u16,
+        min_shares_out,
+        now,
+    )?;
+
+    // Open the position as an NFT when its accounts are passed
+    let position_nft = position_nft_accounts(
+        funder.to_account_info(),
+        beneficiary,
+        ctx.accounts.position_mint.as_deref(),
+        ctx.accounts.position_nft.as_deref(),
+        ctx.accounts.position_token_program.as_ref(),
+        &ctx.accounts.system_program,
+    )?;
+    if let Some(position_nft) = position_nft {
+        require!(opened, StakingError::InvalidPosition);
+        mint_position_nft(position_nft, pool, user_stake)?;
+    }
+
+    emit!(StakeForEvent {
+        pool: pool.key(),
+        funder: funder.key(),
+        beneficiary,
+        position_index,
+        stake_amount,
+        deposit_fee,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+#[instruction(beneficiary: Pubkey, stake_amount: u64, position_index: u64)]
+pub struct StakeFor<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(mut, has_one = stake_vault)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        constraint = funder_stake_ata.mint == pool.stake_mint
+    )]
+    pub funder_stake_ata: InterfaceAccount<'info, TokenAccount>, // funder's token account for stake token
+
+    #[account(constraint = stake_mint.key() == pool.stake_mint)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, address = pool.stake_vault)]
+    pub stake_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [USER_STAKE_SEED.as_bytes(), pool.key().as_ref(), beneficiary.as_ref(), &position_index.to_le_bytes()],
+        bump,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + PositionCounter::INIT_SPACE,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(), pool.key().as_ref(), beneficiary.as_ref()],
+        bump
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
+    /// Required when the pool charges a deposit fee
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the beneficiary's wallet, required to open the position as an NFT
+    #[account(address = beneficiary @ StakingError::InvalidOwner)]
+    pub beneficiary_wallet: Option<UncheckedAccount<'info>>,
+
+    /// Required to open the position as an NFT, only when the position is new
+    #[account(
+        init,
+        payer = funder,
+        seeds = [POSITION_MINT_SEED.as_bytes(), user_stake.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool,
+        mint::freeze_authority = pool,
+        mint::token_program = position_token_program,
+        extensions::metadata_pointer::authority = pool,
+        extensions::metadata_pointer::metadata_address = position_mint,
+    )]
+    pub position_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(
+        init,
+        payer = funder,
+        associated_token::mint = position_mint,
+        associated_token::authority = beneficiary_wallet,
+        associated_token::token_program = position_token_program,
+    )]
+    pub position_nft: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub position_token_program: Option<Program<'info, Token2022>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
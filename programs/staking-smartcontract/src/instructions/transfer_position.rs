@@ -0,0 +1,190 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::instructions::{mint_position_nft, position_nft_accounts};
+use crate::states::{POSITION_COUNTER_SEED, POSITION_MINT_SEED, USER_STAKE_SEED, Pool, PositionCounter, UserStake};
+use crate::utils::{
+    check_deadline, check_user_capacity, StakingError, TransferPositionEvent, move_shares, open_position, stake_value_of,
+    sync_reward_vars,
+};
+
+/// @dev Function to move shares to another wallet without unstaking
+/// @dev Pending rewards stay with the sender's position, the lock moves along with the shares
+/// @dev Shares can go into a new position, or into an existing one of the sender. Moving into another wallet's
+/// existing position changes its lock and loyalty, so that wallet must sign as `recipient`
+/// @dev A new recipient position is opened as an NFT held by the recipient when the position NFT accounts are passed
+/// @dev Moving shares to another wallet moves their stake value to its principal, within the pool's per-user cap
+/// @param `shares` The shares to move
+/// @param `new_owner` The wallet receiving the shares
+/// @param `position_index` Recipient position to move into, the recipient's position count opens a new one
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _transfer_position(
+    ctx: Context<TransferPosition>,
+    shares: u128,
+    new_owner: Pubkey,
+    position_index: u64,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+    require!(new_owner != Pubkey::default(), StakingError::InvalidOwner);
+    require!(ctx.accounts.recipient_stake.key() != ctx.accounts.user_stake.key(), StakingError::InvalidPosition);
+    require!(
+        ctx.accounts.recipient_stake.owner == Pubkey::default()
+            || new_owner == ctx.accounts.user.key()
+            || ctx.accounts.recipient.is_some(),
+        StakingError::InvalidOwner
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &mut ctx.accounts.pool;
+    let user_stake = &mut ctx.accounts.user_stake;
+    let recipient_stake = &mut ctx.accounts.recipient_stake;
+
+    let opened = recipient_stake.owner == Pubkey::default();
+    open_position(
+        &mut ctx.accounts.recipient_position_counter,
+        ctx.bumps.recipient_position_counter,
+        recipient_stake,
+        ctx.bumps.recipient_stake,
+        new_owner,
+        pool.key(),
+        position_index,
+    )?;
+
+    // Sync rewards before changing balances
+    sync_reward_vars(pool, now)?;
+
+    // Value the shares before the move, while the sender still holds them
+    let moved_stake = stake_value_of(pool, shares)?;
+    move_shares(pool, user_stake, recipient_stake, shares, now)?;
+
+    // Shares moved to another wallet take their principal along, the sender's counter is only needed then
+    if new_owner != ctx.accounts.user.key() {
+        let position_counter = ctx.accounts.position_counter.as_mut().ok_or(StakingError::MissingPositionCounter)?;
+        position_counter.total_principal = position_counter.total_principal.saturating_sub(moved_stake);
+
+        let recipient_counter = &mut ctx.accounts.recipient_position_counter;
+        let total_principal_after = recipient_counter.total_principal.checked_add(moved_stake).ok_or(StakingError::Overflow)?;
+        check_user_capacity(pool, total_principal_after)?;
+        recipient_counter.total_principal = total_principal_after;
+    }
+
+    // Open the position as an NFT when its accounts are passed
+    let position_nft = position_nft_accounts(
+        ctx.accounts.user.to_account_info(),
+        new_owner,
+        ctx.accounts.position_mint.as_deref(),
+        ctx.accounts.position_nft.as_deref(),
+        ctx.accounts.position_token_program.as_ref(),
+        &ctx.accounts.system_program,
+    )?;
+    if let Some(position_nft) = position_nft {
+        require!(opened, StakingError::InvalidPosition);
+        mint_position_nft(position_nft, pool, recipient_stake)?;
+    }
+
+    emit!(TransferPositionEvent {
+        pool: pool.key(),
+        from: ctx.accounts.user.key(),
+        from_position: user_stake.position_index,
+        to: new_owner,
+        to_position: position_index,
+        shares,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+#[instruction(shares: u128, new_owner: Pubkey, position_index: u64)]
+pub struct TransferPosition<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [
+            USER_STAKE_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user.key().as_ref(),
+            &user_stake.position_index.to_le_bytes(),
+        ],
+        bump = user_stake.bump,
+        constraint = user_stake.owner == user.key() @ StakingError::InvalidOwner,
+        constraint = user_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub user_stake: Account<'info, UserStake>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + UserStake::INIT_SPACE,
+        seeds = [USER_STAKE_SEED.as_bytes(), pool.key().as_ref(), new_owner.as_ref(), &position_index.to_le_bytes()],
+        bump,
+        constraint = recipient_stake.position_mint == Pubkey::default() @ StakingError::PositionIsNft,
+    )]
+    pub recipient_stake: Account<'info, UserStake>,
+
+    /// The sender's position counter, required when moving shares to another wallet. Declared before the recipient's
+    /// counter so that one is written last when both are the same account
+    #[account(
+        mut,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(), pool.key().as_ref(), user.key().as_ref()],
+        bump = position_counter.bump,
+    )]
+    pub position_counter: Option<Account<'info, PositionCounter>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + PositionCounter::INIT_SPACE,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(), pool.key().as_ref(), new_owner.as_ref()],
+        bump
+    )]
+    pub recipient_position_counter: Account<'info, PositionCounter>,
+
+    /// Required when moving into an existing position of another wallet
+    #[account(address = new_owner @ StakingError::InvalidOwner)]
+    pub recipient: Option<Signer<'info>>,
+
+    /// CHECK: the recipient's wallet, required to open the position as an NFT
+    #[account(address = new_owner @ StakingError::InvalidOwner)]
+    pub recipient_wallet: Option<UncheckedAccount<'info>>,
+
+    /// Required to open the position as an NFT, only when the position is new
+    #[account(
+        init,
+        payer = user,
+        seeds = [POSITION_MINT_SEED.as_bytes(), recipient_stake.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool,
+        mint::freeze_authority = pool,
+        mint::token_program = position_token_program,
+        extensions::metadata_pointer::authority = pool,
+        extensions::metadata_pointer::metadata_address = position_mint,
+    )]
+    pub position_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = position_mint,
+        associated_token::authority = recipient_wallet,
+        associated_token::token_program = position_token_program,
+    )]
+    pub position_nft: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub position_token_program: Option<Program<'info, Token2022>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+
+    pub system_program: Program<'info, System>,
+}
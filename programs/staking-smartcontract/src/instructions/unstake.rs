@@ -1,12 +1,46 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Burn, Mint, TokenAccount, TokenInterface, TransferChecked};
 
-use crate::states::{POOL_SEED, Pool, UserStake};
-use crate::utils::{SCALING_FACTOR, StakingError, sync_reward_vars, user_pending_reward, UnstakeEvent};
+use crate::states::{
+    FEE_VAULT_SEED, POOL_SEED, POSITION_COUNTER_SEED, REFERRER_SEED, REWARD_VESTING_SEED, REWARD_VESTING_VAULT_SEED, Pool,
+    PositionCounter, ReferrerAccount, RewardVesting, UserStake,
+};
+use crate::instructions::{pay_reward, RewardCuts, RewardPayout};
+use crate::utils::{
+    BurnPositionNftEvent, ForfeitVestingEvent, StakingError, UnstakeEvent, bps_of, check_deadline, check_vesting_recipient,
+    checkpoint_epoch, distribute_reward, is_position_holder, quote_unstake_shares, reduce_epoch_shares, reward_recipient_for,
+    settle_vesting, sync_reward_vars, take_reward_budget, update_user_weight, user_pending_reward,
+};
 
-/// @dev Function to unstake / withdraw the staked tokens
-pub fn _unstake(ctx: Context<Unstake>, shares: u128) -> Result<()> {
+/// @dev Function to unstake / withdraw the staked tokens -- ONLY the owner or the holder of the position NFT,
+/// never the claim delegate
+/// @dev A full exit burns the position NFT
+/// @param `shares` The shares to unstake
+/// @param `min_amount_out` The least stake tokens to receive, guarding against a moving share price
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _unstake(mut ctx: Context<Unstake>, shares: u128, min_amount_out: u64, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+
+    let (amount, withdraw_fee) = quote_unstake_shares(&ctx.accounts.pool, shares)?;
+
+    unstake_core(&mut ctx, shares, amount, withdraw_fee, min_amount_out)
+}
+
+/// @dev Shared core of the unstake instructions -- burns `shares` worth `amount` of stake, pays the pending rewards
+/// and returns `amount` net of `withdraw_fee`, failing when that is below `min_amount_out`
+pub fn unstake_core(
+    ctx: &mut Context<Unstake>,
+    shares: u128,
+    amount: u128,
+    withdraw_fee: u64,
+    min_amount_out: u64,
+) -> Result<()> {
     require!(!ctx.accounts.pool.paused, StakingError::Paused);
+    require!(
+        is_position_holder(&ctx.accounts.user_stake, &ctx.accounts.user.key(), ctx.accounts.position_nft.as_deref()),
+        StakingError::InvalidOwner
+    );
 
     let now = Clock::get()?.unix_timestamp;
     let pool = &mut ctx.accounts.pool;
@@ -19,41 +53,46 @@ pub fn _unstake(ctx: Context<Unstake>, shares: u128) -> Result<()> {
 
     require!(shares > 0, StakingError::InvalidAmount);
     require!(user_stake.shares >= shares, StakingError::InsufficientShares);
+    require!(user_stake.lock_end <= now, StakingError::StakeLocked);
 
     // Sync the reward states
     sync_reward_vars(pool, now)?;
+    checkpoint_epoch(pool, user_stake, now)?;
 
     // Check if there are pending rewards, if yes -- then send it to user
     let pending_reward = user_pending_reward(user_stake, pool)?;
+    let pending_reward = take_reward_budget(pool, pending_reward)?;
 
-    if pending_reward > 0u128 {
-        let pending_reward_u64 = pending_reward.try_into().map_err(|_| StakingError::Overflow)?;
-        // Seeds that will be used for signing the transaction
-        let binding = ctx.accounts.stake_mint.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), binding.as_ref(), &[ctx.bumps.pool]]];
+    // Seeds that will be used for signing the transaction
+    let binding = ctx.accounts.stake_mint.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), binding.as_ref(), &[ctx.bumps.pool]]];
 
-        // Prepare and call the mint function
-        let cpi_accounts = MintTo {
-            mint: reward_mint.to_account_info(),
-            to: user_reward_ata.to_account_info(),
-            authority: pool.to_account_info(),
-        };
-
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-
-        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
-        token_interface::mint_to(cpi_context, pending_reward_u64)?;
-    }
-
-    // Compute amount of stake tokens to return --> shares * total_staked / total_shares
-    let prod = shares.checked_mul(pool.total_stake).ok_or(StakingError::Overflow)?;
-    let amount_u128 = prod.checked_div(pool.total_shares).ok_or(StakingError::Overflow)?;
-    let amount_u64 = amount_u128.try_into().map_err(|_| StakingError::Overflow)?;
+    let recipient = reward_recipient_for(user_stake, &ctx.accounts.user.key());
+    let payout = RewardPayout {
+        cuts: RewardCuts {
+            pool,
+            reward_mint,
+            referrer_account: ctx.accounts.referrer_account.as_mut(),
+            referrer_bump: ctx.bumps.referrer_account,
+            treasury_reward_ata: ctx.accounts.treasury_reward_ata.as_ref(),
+            token_program: &ctx.accounts.token_program,
+        },
+        position: user_stake.key(),
+        recipient,
+        recipient_ata: user_reward_ata,
+        reward_vesting: ctx.accounts.reward_vesting.as_mut(),
+        reward_vesting_bump: ctx.bumps.reward_vesting,
+        vesting_vault: ctx.accounts.vesting_vault.as_ref(),
+    };
+    let (reward_paid, fee_paid) = pay_reward(payout, user_stake, pending_reward, now)?;
 
-    let binding = pool.key();
-    let stake_vault_seeds: &[&[&[u8]]] = &[&[b"stake_vault", binding.as_ref(), &[ctx.bumps.stake_vault]]];
+    // Take the withdrawal fee out of the returned amount
+    let amount_u128 = amount;
+    let amount_u64: u64 = amount_u128.try_into().map_err(|_| StakingError::Overflow)?;
+    let net_amount = amount_u64.checked_sub(withdraw_fee).ok_or(StakingError::Overflow)?;
+    require!(net_amount >= min_amount_out, StakingError::SlippageExceeded);
 
-    // Prepare and transfer the unstaked shares
+    // Prepare and transfer the unstaked shares -- the vault is owned by the pool, which signs
     let cpi_transfer_accounts = TransferChecked {
         from: stake_vault.to_account_info(),
         to: user_stake_ata.to_account_info(),
@@ -63,24 +102,135 @@ pub fn _unstake(ctx: Context<Unstake>, shares: u128) -> Result<()> {
 
     let cpi_transfer_program = ctx.accounts.token_program.to_account_info();
 
-    let cpi_transfer_context = CpiContext::new(cpi_transfer_program, cpi_transfer_accounts).with_signer(stake_vault_seeds);
+    let cpi_transfer_context = CpiContext::new(cpi_transfer_program, cpi_transfer_accounts).with_signer(signer_seeds);
+
+    token_interface::transfer_checked(cpi_transfer_context, net_amount, stake_mint.decimals)?;
+
+    // Transfer the withdrawal fee from stake_vault --> fee_vault
+    if withdraw_fee > 0u64 {
+        let fee_vault = ctx.accounts.fee_vault.as_ref().ok_or(StakingError::MissingFeeVault)?;
+
+        let cpi_accounts = TransferChecked {
+            from: stake_vault.to_account_info(),
+            to: fee_vault.to_account_info(),
+            mint: stake_mint.to_account_info(),
+            authority: pool.to_account_info(),
+        };
 
-    token_interface::transfer_checked(cpi_transfer_context, amount_u64, stake_mint.decimals)?;
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+        token_interface::transfer_checked(cpi_context, withdraw_fee, stake_mint.decimals)?;
+    }
 
     // Update states
     pool.total_stake = pool.total_stake.checked_sub(amount_u128).ok_or(StakingError::Overflow)?;
     pool.total_shares = pool.total_shares.checked_sub(shares).ok_or(StakingError::Overflow)?;
 
     user_stake.shares = user_stake.shares.checked_sub(shares).ok_or(StakingError::Overflow)?;
+    reduce_epoch_shares(pool, user_stake)?;
+
+    // Free the owner's capacity, compounded rewards can take out more than was deposited
+    let position_counter = &mut ctx.accounts.position_counter;
+    position_counter.total_principal = position_counter.total_principal.saturating_sub(amount_u128);
+
+    // Unstaking interrupts the loyalty period, losing `loyalty_decay_bps` of the time accrued so far
+    let staked_for = (now - user_stake.last_stake_time).max(0) as u128;
+    let kept: i64 = (staked_for - bps_of(staked_for, pool.loyalty_decay_bps)?).try_into().map_err(|_| StakingError::Overflow)?;
+    user_stake.last_stake_time = now - kept;
+
+    user_stake.unclaimed_reward = 0u128;
+    update_user_weight(pool, user_stake, now)?;
+
+    // On a full exit, forfeit the position's still-unvested rewards back to the remaining stakers. The rewards vest
+    // to one recipient at a time, so the schedule of the exiting recipient is the only one that can still be locked
+    if user_stake.shares == 0u128 && pool.forfeit_unvested_on_exit {
+        check_vesting_recipient(user_stake, &recipient, now)?;
+
+        let (reward_vesting, vesting_vault) = match (ctx.accounts.reward_vesting.as_mut(), &ctx.accounts.vesting_vault) {
+            (Some(reward_vesting), Some(vesting_vault)) => (reward_vesting, vesting_vault),
+            _ => return err!(StakingError::MissingVestingAccount),
+        };
+
+        settle_vesting(reward_vesting, now)?;
+
+        let forfeited = reward_vesting.total_amount.checked_sub(reward_vesting.released_amount).ok_or(StakingError::Overflow)?;
+
+        if forfeited > 0u64 {
+            // The vested part is already in `unlocked_amount`, so the schedule is cleared
+            reward_vesting.total_amount = 0u64;
+            reward_vesting.released_amount = 0u64;
+
+            // Burn the forfeited rewards, they are re-emitted through the accumulator
+            let cpi_accounts = Burn {
+                mint: reward_mint.to_account_info(),
+                from: vesting_vault.to_account_info(),
+                authority: pool.to_account_info(),
+            };
 
-    let debt_prod = user_stake.shares.checked_mul(pool.acc_reward_per_share).ok_or(StakingError::Overflow)?;
-    user_stake.reward_debt = debt_prod.checked_div(SCALING_FACTOR).ok_or(StakingError::Overflow)?;
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+
+            let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+            token_interface::burn(cpi_context, forfeited)?;
+
+            distribute_reward(pool, forfeited as u128)?;
+
+            // The burned rewards are no longer paid out, and count against the budget again once re-claimed
+            pool.total_rewards_paid = pool.total_rewards_paid.saturating_sub(forfeited as u128);
+
+            emit!(ForfeitVestingEvent {
+                pool: pool.key(),
+                user: reward_vesting.owner,
+                amount: forfeited,
+            });
+        }
+
+        // Nothing of the position vests anymore
+        user_stake.vesting_recipient = Pubkey::default();
+        user_stake.vesting_end = now;
+    }
+
+    // A full exit burns the position NFT
+    if user_stake.shares == 0u128 && user_stake.position_mint != Pubkey::default() {
+        let (position_nft, position_mint, position_token_program) = match (
+            &ctx.accounts.position_nft,
+            &ctx.accounts.position_mint,
+            &ctx.accounts.position_token_program,
+        ) {
+            (Some(position_nft), Some(position_mint), Some(position_token_program)) => {
+                (position_nft, position_mint, position_token_program)
+            }
+            _ => return err!(StakingError::MissingPositionNft),
+        };
+
+        let cpi_accounts = Burn {
+            mint: position_mint.to_account_info(),
+            from: position_nft.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+
+        let cpi_program = position_token_program.to_account_info();
+
+        let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
+        token_interface::burn(cpi_context, 1u64)?;
+
+        user_stake.position_mint = Pubkey::default();
+
+        emit!(BurnPositionNftEvent {
+            pool: pool.key(),
+            holder: ctx.accounts.user.key(),
+            user_stake: user_stake.key(),
+            position_mint: position_mint.key(),
+        });
+    }
 
     emit!(UnstakeEvent {
         pool: pool.key(),
         user: ctx.accounts.user.key(),
-        unstaked_amount: amount_u64,
-        reward_amount: pending_reward,
+        unstaked_amount: net_amount,
+        withdraw_fee,
+        reward_amount: reward_paid as u128,
+        fee_amount: fee_paid,
     });
 
     Ok(())
@@ -91,9 +241,19 @@ pub struct Unstake<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_stake.pool == pool.key() @ StakingError::InvalidPool,
+    )]
     pub user_stake: Account<'info, UserStake>,
 
+    #[account(
+        mut,
+        seeds = [POSITION_COUNTER_SEED.as_bytes(), pool.key().as_ref(), user_stake.owner.as_ref()],
+        bump = position_counter.bump,
+    )]
+    pub position_counter: Account<'info, PositionCounter>,
+
     #[account(
         mut,
         seeds = [POOL_SEED.as_bytes(), stake_mint.key().as_ref()],
@@ -117,9 +277,72 @@ pub struct Unstake<'info> {
     #[account(mut)]
     pub user_stake_ata: InterfaceAccount<'info, TokenAccount>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_reward_ata.mint == pool.reward_mint,
+        constraint = user_reward_ata.owner == reward_recipient_for(&user_stake, &user.key()) @ StakingError::InvalidOwner,
+    )]
     pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
 
+    /// Required when the pool vests claimed rewards, and on a full exit when unvested rewards are forfeited
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + RewardVesting::INIT_SPACE,
+        seeds = [
+            REWARD_VESTING_SEED.as_bytes(),
+            pool.key().as_ref(),
+            user_stake.key().as_ref(),
+            reward_recipient_for(&user_stake, &user.key()).as_ref(),
+        ],
+        bump
+    )]
+    pub reward_vesting: Option<Account<'info, RewardVesting>>,
+
+    #[account(
+        mut,
+        seeds = [REWARD_VESTING_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub vesting_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the position has a referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + ReferrerAccount::INIT_SPACE,
+        seeds = [REFERRER_SEED.as_bytes(), pool.key().as_ref(), user_stake.referrer.as_ref()],
+        bump
+    )]
+    pub referrer_account: Option<Account<'info, ReferrerAccount>>,
+
+    /// Required when the pool charges a protocol fee
+    #[account(
+        mut,
+        constraint = treasury_reward_ata.mint == pool.reward_mint,
+        constraint = treasury_reward_ata.owner == pool.fee_recipient @ StakingError::InvalidOwner,
+    )]
+    pub treasury_reward_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the pool charges a withdrawal fee
+    #[account(
+        mut,
+        seeds = [FEE_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub fee_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required when the position is an NFT, the user's token account holding it
+    #[account(mut)]
+    pub position_nft: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Required to burn the position NFT on a full exit
+    #[account(mut, address = user_stake.position_mint @ StakingError::InvalidPosition)]
+    pub position_mint: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Required to burn the position NFT on a full exit
+    pub position_token_program: Option<Program<'info, Token2022>>,
+
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
\ No newline at end of file
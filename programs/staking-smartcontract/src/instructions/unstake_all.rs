@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::{unstake_core, Unstake};
+use crate::utils::{check_deadline, StakingError, quote_unstake_shares};
+
+/// @dev Function to unstake all shares of the position and pay its rewards
+/// @dev Closing needs the position's rewards, including those of finished epochs, to be claimed
+/// @param `min_amount_out` The least stake tokens to receive, guarding against a moving share price
+/// @param `close` Whether to close the position afterwards, returning its rent -- ONLY by its owner
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _unstake_all(mut ctx: Context<Unstake>, min_amount_out: u64, close: bool, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let shares = ctx.accounts.user_stake.shares;
+
+    if shares > 0u128 {
+        let (amount, withdraw_fee) = quote_unstake_shares(&ctx.accounts.pool, shares)?;
+
+        unstake_core(&mut ctx, shares, amount, withdraw_fee, min_amount_out)?;
+    }
+
+    if close {
+        let user_stake = &ctx.accounts.user_stake;
+        require!(user_stake.owner == ctx.accounts.user.key(), StakingError::InvalidOwner);
+        require!(user_stake.unclaimed_reward == 0u128, StakingError::UnclaimedRewards);
+
+        // A finished epoch the position was still eligible for must be claimed first
+        let pool = &ctx.accounts.pool;
+        let unclaimed_epoch = pool.epoch_duration > 0i64
+            && user_stake.epoch_shares > 0u128
+            && user_stake.epoch_checkpoint >= user_stake.next_claim_epoch.max(1u64)
+            && user_stake.epoch_checkpoint < pool.current_epoch;
+        require!(!unclaimed_epoch, StakingError::UnclaimedEpochs);
+
+        user_stake.close(ctx.accounts.user.to_account_info())?;
+    }
+
+    Ok(())
+}
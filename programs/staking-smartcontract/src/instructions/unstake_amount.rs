@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::{unstake_core, Unstake};
+use crate::utils::{check_deadline, StakingError, quote_unstake_amount};
+
+/// @dev Function to unstake so that exactly `amount` of stake tokens is returned, after the withdrawal fee
+/// @dev The shares burned are rounded up, in favour of the pool
+/// @param `amount` The amount to receive
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _unstake_amount(mut ctx: Context<Unstake>, amount: u64, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(amount > 0u64, StakingError::InvalidAmount);
+
+    let (shares, gross_amount, withdraw_fee) = quote_unstake_amount(&ctx.accounts.pool, amount)?;
+
+    unstake_core(&mut ctx, shares, gross_amount, withdraw_fee, amount)
+}
@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::states::{POOL_SEED, REWARD_VESTING_SEED, REWARD_VESTING_VAULT_SEED, Pool, RewardVesting};
+use crate::utils::{check_deadline, settle_vesting, StakingError, WithdrawVestedEvent};
+
+/// @dev Function to withdraw the rewards that have vested so far
+/// @dev Rewards vest per position, `position` is the one they were earned by and may already be closed
+/// @param `deadline` Unix time the instruction must execute by, none to never expire
+pub fn _withdraw_vested(ctx: Context<WithdrawVested>, deadline: Option<i64>) -> Result<()> {
+    check_deadline(deadline)?;
+    require!(!ctx.accounts.pool.paused, StakingError::Paused);
+
+    let now = Clock::get()?.unix_timestamp;
+    let pool = &ctx.accounts.pool;
+    let reward_vesting = &mut ctx.accounts.reward_vesting;
+    let reward_mint = &ctx.accounts.reward_mint;
+
+    settle_vesting(reward_vesting, now)?;
+
+    let amount = reward_vesting.unlocked_amount;
+    if amount == 0u64 {
+        return Ok(());
+    }
+
+    reward_vesting.unlocked_amount = 0u64;
+
+    // Seeds that will be used for signing the transaction
+    let signer_seeds: &[&[&[u8]]] = &[&[POOL_SEED.as_bytes(), pool.stake_mint.as_ref(), &[pool.bump]]];
+
+    // Transfer from vesting vault --> user
+    let cpi_accounts = TransferChecked {
+        mint: reward_mint.to_account_info(),
+        from: ctx.accounts.vesting_vault.to_account_info(),
+        to: ctx.accounts.user_reward_ata.to_account_info(),
+        authority: pool.to_account_info(),
+    };
+
+    let cpi_program = ctx.accounts.token_program.to_account_info();
+
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer_seeds);
+    token_interface::transfer_checked(cpi_context, amount, reward_mint.decimals)?;
+
+    emit!(WithdrawVestedEvent {
+        pool: pool.key(),
+        user: ctx.accounts.user.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+//------------------------------------ ACCOUNTS ------------------------------------//
+
+#[derive(Accounts)]
+pub struct WithdrawVested<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [POOL_SEED.as_bytes(), pool.stake_mint.as_ref()],
+        bump = pool.bump,
+        has_one = reward_mint,
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: position the rewards vested from, only used as a seed
+    pub position: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [REWARD_VESTING_SEED.as_bytes(), pool.key().as_ref(), position.key().as_ref(), user.key().as_ref()],
+        bump = reward_vesting.bump,
+    )]
+    pub reward_vesting: Account<'info, RewardVesting>,
+
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [REWARD_VESTING_VAULT_SEED.as_bytes(), pool.key().as_ref()],
+        bump,
+    )]
+    pub vesting_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
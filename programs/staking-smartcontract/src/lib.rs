@@ -5,6 +5,7 @@ pub mod instructions;
 pub mod utils;
 
 use crate::instructions::*;
+use crate::states::{EmissionSegment, RateCurve};
 
 declare_id!("7EwcQih3qmU9G95UTmxYbSfoyfvHME6hWLUuCb3Qef2Z");
 
@@ -13,20 +14,50 @@ pub mod staking_smartcontract {
 
     use super::*;
 
-    pub fn initialize_pool(ctx: Context<InitializePool>, reward_rate: u64) -> Result<()> {
-        _initialize_pool(ctx, reward_rate)
+    pub fn initialize_pool(ctx: Context<InitializePool>, reward_rate: u64, start_time: i64) -> Result<()> {
+        _initialize_pool(ctx, reward_rate, start_time)
     }
 
-    pub fn stake(ctx: Context<Stake>, stake_amount: u64) -> Result<()> {
-        _stake(ctx, stake_amount)
+    pub fn stake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Stake<'info>>,
+        stake_amount: u64,
+        lock_weeks: u16,
+        referrer: Option<Pubkey>,
+        position_index: u64,
+        min_shares_out: u128,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _stake(ctx, stake_amount, lock_weeks, referrer, position_index, min_shares_out, deadline)
     }
 
-    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
-        _claim_reward(ctx)
+    pub fn stake_for(
+        ctx: Context<StakeFor>,
+        beneficiary: Pubkey,
+        stake_amount: u64,
+        position_index: u64,
+        min_shares_out: u128,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _stake_for(ctx, beneficiary, stake_amount, position_index, min_shares_out, deadline)
     }
 
-    pub fn unstake(ctx: Context<Unstake>, shares: u128) -> Result<()> {
-        _unstake(ctx, shares)
+    pub fn claim_reward<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimReward<'info>>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _claim_reward(ctx, deadline)
+    }
+
+    pub fn unstake(ctx: Context<Unstake>, shares: u128, min_amount_out: u64, deadline: Option<i64>) -> Result<()> {
+        _unstake(ctx, shares, min_amount_out, deadline)
+    }
+
+    pub fn unstake_amount(ctx: Context<Unstake>, amount: u64, deadline: Option<i64>) -> Result<()> {
+        _unstake_amount(ctx, amount, deadline)
+    }
+
+    pub fn unstake_all(ctx: Context<Unstake>, min_amount_out: u64, close: bool, deadline: Option<i64>) -> Result<()> {
+        _unstake_all(ctx, min_amount_out, close, deadline)
     }
 
     pub fn set_pause(ctx: Context<SetPause>, paused: bool) -> Result<()> {
@@ -36,4 +67,220 @@ pub mod staking_smartcontract {
     pub fn set_reward(ctx: Context<SetReward>, reward_rate: u64) -> Result<()> {
         _set_reward(ctx, reward_rate)
     }
+
+    pub fn compound(ctx: Context<Compound>, deadline: Option<i64>) -> Result<()> {
+        _compound(ctx, deadline)
+    }
+
+    pub fn set_auto_compound(
+        ctx: Context<SetAutoCompound>,
+        auto_compound: bool,
+        max_keeper_fee_bps: u16,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _set_auto_compound(ctx, auto_compound, max_keeper_fee_bps, deadline)
+    }
+
+    pub fn set_keeper_fee(ctx: Context<SetKeeperFee>, keeper_fee_bps: u16) -> Result<()> {
+        _set_keeper_fee(ctx, keeper_fee_bps)
+    }
+
+    pub fn crank_compound<'info>(ctx: Context<'_, '_, 'info, 'info, CrankCompound<'info>>) -> Result<()> {
+        _crank_compound(ctx)
+    }
+
+    pub fn set_vesting(
+        ctx: Context<SetVesting>,
+        vesting_duration: i64,
+        vesting_cliff: i64,
+        forfeit_unvested_on_exit: bool,
+    ) -> Result<()> {
+        _set_vesting(ctx, vesting_duration, vesting_cliff, forfeit_unvested_on_exit)
+    }
+
+    pub fn withdraw_vested(ctx: Context<WithdrawVested>, deadline: Option<i64>) -> Result<()> {
+        _withdraw_vested(ctx, deadline)
+    }
+
+    pub fn set_lock_config(ctx: Context<SetLockConfig>, max_lock_weeks: u16, max_boost_bps: u16) -> Result<()> {
+        _set_lock_config(ctx, max_lock_weeks, max_boost_bps)
+    }
+
+    pub fn kick(ctx: Context<Kick>) -> Result<()> {
+        _kick(ctx)
+    }
+
+    pub fn set_loyalty_config(
+        ctx: Context<SetLoyaltyConfig>,
+        loyalty_max_bps: u16,
+        loyalty_period: i64,
+        loyalty_decay_bps: u16,
+    ) -> Result<()> {
+        _set_loyalty_config(ctx, loyalty_max_bps, loyalty_period, loyalty_decay_bps)
+    }
+
+    pub fn set_referral(ctx: Context<SetReferral>, referral_bps: u16) -> Result<()> {
+        _set_referral(ctx, referral_bps)
+    }
+
+    pub fn claim_referral_reward(ctx: Context<ClaimReferralReward>, deadline: Option<i64>) -> Result<()> {
+        _claim_referral_reward(ctx, deadline)
+    }
+
+    pub fn set_reward_recipient(
+        ctx: Context<SetRewardRecipient>,
+        reward_recipient: Pubkey,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _set_reward_recipient(ctx, reward_recipient, deadline)
+    }
+
+    pub fn set_claim_delegate(
+        ctx: Context<SetClaimDelegate>,
+        claim_delegate: Pubkey,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _set_claim_delegate(ctx, claim_delegate, deadline)
+    }
+
+    pub fn set_protocol_fee(ctx: Context<SetProtocolFee>, fee_bps: u16, fee_recipient: Pubkey) -> Result<()> {
+        _set_protocol_fee(ctx, fee_bps, fee_recipient)
+    }
+
+    pub fn set_principal_fees(ctx: Context<SetPrincipalFees>, deposit_fee_bps: u16, withdraw_fee_bps: u16) -> Result<()> {
+        _set_principal_fees(ctx, deposit_fee_bps, withdraw_fee_bps)
+    }
+
+    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+        _collect_fees(ctx)
+    }
+
+    pub fn set_emission_schedule(
+        ctx: Context<SetEmissionSchedule>,
+        segments: Vec<EmissionSegment>,
+        halving_interval: i64,
+    ) -> Result<()> {
+        _set_emission_schedule(ctx, segments, halving_interval)
+    }
+
+    pub fn set_reward_budget(ctx: Context<SetRewardBudget>, max_total_rewards: u128) -> Result<()> {
+        _set_reward_budget(ctx, max_total_rewards)
+    }
+
+    pub fn set_reward_apr(
+        ctx: Context<SetRewardApr>,
+        reward_apr_bps: u32,
+        price_ratio_num: u64,
+        price_ratio_den: u64,
+    ) -> Result<()> {
+        _set_reward_apr(ctx, reward_apr_bps, price_ratio_num, price_ratio_den)
+    }
+
+    pub fn set_rate_curve(ctx: Context<SetRateCurve>, rate_curve: RateCurve) -> Result<()> {
+        _set_rate_curve(ctx, rate_curve)
+    }
+
+    pub fn set_epoch_mode(ctx: Context<SetEpochMode>, epoch_duration: i64) -> Result<()> {
+        _set_epoch_mode(ctx, epoch_duration)
+    }
+
+    pub fn advance_epoch(ctx: Context<AdvanceEpoch>) -> Result<()> {
+        _advance_epoch(ctx)
+    }
+
+    pub fn claim_epoch(ctx: Context<ClaimEpoch>, epoch: u64, deadline: Option<i64>) -> Result<()> {
+        _claim_epoch(ctx, epoch, deadline)
+    }
+
+    pub fn set_bonus_distribution(
+        ctx: Context<SetBonusDistribution>,
+        merkle_root: [u8; 32],
+        total_amount: u64,
+        expiry: i64,
+    ) -> Result<()> {
+        _set_bonus_distribution(ctx, merkle_root, total_amount, expiry)
+    }
+
+    pub fn claim_bonus(
+        ctx: Context<ClaimBonus>,
+        index: u32,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _claim_bonus(ctx, index, amount, proof, deadline)
+    }
+
+    pub fn reclaim_bonus(ctx: Context<ReclaimBonus>) -> Result<()> {
+        _reclaim_bonus(ctx)
+    }
+
+    pub fn set_start_time(ctx: Context<SetStartTime>, start_time: i64, allow_pre_deposit: bool) -> Result<()> {
+        _set_start_time(ctx, start_time, allow_pre_deposit)
+    }
+
+    pub fn set_deposit_limits(
+        ctx: Context<SetDepositLimits>,
+        deposit_open: i64,
+        deposit_close: i64,
+        max_total_stake: u128,
+        max_user_stake: u128,
+        min_stake_amount: u64,
+    ) -> Result<()> {
+        _set_deposit_limits(ctx, deposit_open, deposit_close, max_total_stake, max_user_stake, min_stake_amount)
+    }
+
+    pub fn create_term_product(
+        ctx: Context<CreateTermProduct>,
+        term_id: u16,
+        duration: i64,
+        apy_bps: u32,
+        allow_early_redeem: bool,
+        early_redeem_penalty_bps: u16,
+    ) -> Result<()> {
+        _create_term_product(ctx, term_id, duration, apy_bps, allow_early_redeem, early_redeem_penalty_bps)
+    }
+
+    pub fn fund_term_rewards(ctx: Context<FundTermRewards>, amount: u64) -> Result<()> {
+        _fund_term_rewards(ctx, amount)
+    }
+
+    pub fn open_term_deposit(
+        ctx: Context<OpenTermDeposit>,
+        amount: u64,
+        term_id: u16,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _open_term_deposit(ctx, amount, term_id, deadline)
+    }
+
+    pub fn redeem_term(ctx: Context<RedeemTerm>, deadline: Option<i64>) -> Result<()> {
+        _redeem_term(ctx, deadline)
+    }
+
+    pub fn transfer_position(
+        ctx: Context<TransferPosition>,
+        shares: u128,
+        new_owner: Pubkey,
+        position_index: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        _transfer_position(ctx, shares, new_owner, position_index, deadline)
+    }
+
+    pub fn split_position(ctx: Context<SplitPosition>, shares: u128, deadline: Option<i64>) -> Result<()> {
+        _split_position(ctx, shares, deadline)
+    }
+
+    pub fn merge_positions(ctx: Context<MergePositions>, deadline: Option<i64>) -> Result<()> {
+        _merge_positions(ctx, deadline)
+    }
+
+    pub fn effective_apr(ctx: Context<EffectiveApr>) -> Result<u64> {
+        _effective_apr(ctx)
+    }
+
+    pub fn current_emission_segment(ctx: Context<CurrentEmissionSegment>) -> Result<EmissionSegment> {
+        _current_emission_segment(ctx)
+    }
 }
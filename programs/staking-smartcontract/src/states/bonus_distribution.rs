@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+
+/// Constants
+pub const BONUS_DISTRIBUTION_SEED: &str = "BONUS_DISTRIBUTION";
+pub const BONUS_VAULT_SEED: &str = "BONUS_VAULT";
+pub const MAX_BONUS_CLAIMS: usize = 4_096;
+
+/**
+ * Struct for a pool's merkle-distributed bonus campaign
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct BonusDistribution {
+    pub pool: Pubkey, // The staking pool address
+
+    pub merkle_root: [u8; 32], // Root of the tree of `(index, claimant, amount)` leaves
+    pub expiry: i64, // Bonuses can no longer be claimed from this time, and the rest can be reclaimed
+
+    pub total_amount: u64, // Rewards funded into the bonus vault
+    pub claimed_amount: u64, // Rewards claimed so far
+    pub claimed_bitmap: [u8; MAX_BONUS_CLAIMS / 8], // One bit per leaf index, set once claimed
+
+    pub bump: u8, // Random value to derive bonus distribution pda
+}
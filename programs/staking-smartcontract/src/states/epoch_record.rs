@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+
+
+/// Constants
+pub const EPOCH_RECORD_SEED: &str = "EPOCH_RECORD";
+
+/**
+ * Struct snapshotting a finished epoch of an epoch-mode pool
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct EpochRecord {
+    pub pool: Pubkey, // The staking pool address
+    pub epoch: u64, // Number of the epoch, starting at 1
+
+    pub start_time: i64, // Start of the epoch
+    pub end_time: i64, // End of the epoch
+
+    pub eligible_shares: u128, // Shares held for the whole epoch
+    pub reward_amount: u64, // Rewards distributed over `eligible_shares`
+    pub claimed_amount: u64, // Rewards claimed so far
+
+    pub bump: u8, // Random value to derive epoch record pda
+}
@@ -2,4 +2,22 @@ pub mod user_stake;
 pub use user_stake::*;
 
 pub mod pool;
-pub use pool::*;
\ No newline at end of file
+pub use pool::*;
+
+pub mod reward_vesting;
+pub use reward_vesting::*;
+
+pub mod referrer_account;
+pub use referrer_account::*;
+
+pub mod epoch_record;
+pub use epoch_record::*;
+
+pub mod bonus_distribution;
+pub use bonus_distribution::*;
+
+pub mod term_product;
+pub use term_product::*;
+
+pub mod position_counter;
+pub use position_counter::*;
\ No newline at end of file
@@ -1,8 +1,55 @@
 use anchor_lang::prelude::*;
+use anchor_lang::prelude::borsh;
 
 
 /// Constants
 pub const POOL_SEED: &str = "POOL";
+pub const FEE_VAULT_SEED: &str = "FEE_VAULT";
+pub const MAX_KEEPER_FEE_BPS: u16 = 500; // 5% of a compounded reward
+pub const SECONDS_PER_WEEK: i64 = 604_800;
+pub const MAX_LOCK_WEEKS: u16 = 208; // 4 years
+pub const MAX_BOOST_BPS: u16 = 40_000; // 4x
+pub const MAX_LOYALTY_BPS: u16 = 30_000; // 3x
+pub const MAX_REFERRAL_BPS: u16 = 2_000; // 20% of a referee's rewards
+pub const MAX_REFERRAL_DEPTH: usize = 8; // Longest referral chain walked when checking for loops
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 2_000; // 20% of every reward payout
+pub const MAX_PRINCIPAL_FEE_BPS: u16 = 1_000; // 10% of a deposit or withdrawal
+pub const MAX_EMISSION_SEGMENTS: usize = 8;
+pub const SECONDS_PER_YEAR: i64 = 31_536_000;
+pub const MAX_REWARD_APR_BPS: u32 = 1_000_000; // 10,000% APR
+
+/**
+ * How the pool's emission rate is determined
+ */
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum RewardRateMode {
+    Fixed, // Emits `reward_rate`, or the configured emission schedule
+    Apr, // Emits `reward_apr_bps` of the value of `total_stake` per year
+    Utilization, // Emits the rate of `rate_curve` at the current utilization of `target_stake`
+}
+
+/**
+ * Struct for a kinked reward rate curve over pool utilization
+ * The rate moves linearly from `base_rate` at 0% to `kink_rate` at `kink_bps`,
+ * then to `target_rate` at 100% of `target_stake` and stays there beyond it
+ */
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct RateCurve {
+    pub target_stake: u128, // Stake at which utilization is 100%
+    pub base_rate: u64, // Reward token per second at 0% utilization
+    pub kink_bps: u16, // Utilization of the kink, in basis points
+    pub kink_rate: u64, // Reward token per second at the kink
+    pub target_rate: u64, // Reward token per second at and beyond 100% utilization
+}
+
+/**
+ * Struct for one segment of an emission schedule
+ */
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct EmissionSegment {
+    pub start_time: i64, // The segment is in effect from this time until the next segment starts
+    pub rate: u64, // Reward token per second during the segment
+}
 
 /**
  * Struct for Pool state
@@ -14,13 +61,66 @@ pub struct Pool {
     pub stake_mint: Pubkey, // Address of the staking token
     pub reward_mint: Pubkey, // Address of the reward token
     pub stake_vault: Pubkey, // Address of the vault for storing stake token
+    pub stake_decimals: u8, // Decimals of the staking token
+    pub reward_decimals: u8, // Decimals of the reward token
 
-    pub reward_rate: u64, // Reward token per second
+    pub reward_rate: u64, // Reward token per second, also the starting rate of a halving schedule
     pub total_stake: u128, // Total amount staked in the pool
     pub total_shares: u128, // The sum of all shares minted to all stakers, and it represents 100% of the pool.
+    pub total_weighted_shares: u128, // The sum of all stakers' boosted shares, used to distribute rewards
 
     pub acc_reward_per_share: u128, // Total accumulated rewards per 1 staked token, stored as a scaled number
+    pub reward_remainder: u128, // Accrued rewards too small to move the accumulator yet, carried to the next update
     pub last_update_time: i64, // Last timestamp when rewards were calculated
+    pub start_time: i64, // Rewards accrue from this time
+    pub allow_pre_deposit: bool, // Can users stake before `start_time`
+
+    pub deposit_open: i64, // Deposits are accepted from this time, 0 is no bound
+    pub deposit_close: i64, // Deposits are rejected from this time, 0 is no bound
+    pub max_total_stake: u128, // Cap on `total_stake` after a deposit, 0 is unlimited
+    pub max_user_stake: u128, // Cap on the principal a wallet holds across its positions after a deposit, 0 is unlimited
+    pub min_stake_amount: u64, // Smallest amount a single deposit may stake
+
+    pub emission_segments: [EmissionSegment; MAX_EMISSION_SEGMENTS], // Piecewise emission schedule, sorted by start time
+    pub emission_segment_count: u8, // Number of segments in use, 0 emits `reward_rate`
+    pub halving_interval: i64, // Seconds between halvings of `reward_rate`, 0 disables halving
+    pub halving_start: i64, // Start of the first halving period
+
+    pub max_total_rewards: u128, // Hard cap on the rewards the pool will ever emit, 0 is unlimited
+    pub total_rewards_accrued: u128, // Rewards distributed through the accumulator so far
+    pub total_rewards_paid: u128, // Rewards settled out of the accumulator so far, net of forfeits
+
+    pub reward_rate_mode: RewardRateMode, // Whether the pool emits a fixed rate or targets an APR
+    pub reward_apr_bps: u32, // Targeted APR in `Apr` mode, in basis points
+    pub price_ratio_num: u64, // Value of one whole stake token in whole reward tokens is `num / den`
+    pub price_ratio_den: u64,
+    pub rate_curve: RateCurve, // Utilization curve used in `Utilization` mode
+
+    pub epoch_duration: i64, // Length of a reward epoch in seconds, 0 accrues rewards continuously
+    pub current_epoch: u64, // Epoch in progress, starting at 1
+    pub epoch_start: i64, // Start of the epoch in progress
+    pub epoch_eligible_shares: u128, // Shares held since the start of the epoch in progress
+
+    pub keeper_fee_bps: u16, // Cut of each auto-compounded reward paid to the keeper, in basis points
+
+    pub vesting_duration: i64, // Seconds over which claimed rewards vest, 0 pays rewards out directly
+    pub vesting_cliff: i64, // Seconds after a claim before any of it vests
+    pub forfeit_unvested_on_exit: bool, // Are unvested rewards forfeited to the pool when a user fully exits
+
+    pub max_lock_weeks: u16, // Longest lock a staker can choose, 0 disables locking
+    pub max_boost_bps: u16, // Reward boost for a lock of `max_lock_weeks`, in basis points
+
+    pub loyalty_max_bps: u16, // Loyalty multiplier reached after `loyalty_period`, in basis points
+    pub loyalty_period: i64, // Seconds of uninterrupted staking to reach the max loyalty multiplier
+    pub loyalty_decay_bps: u16, // Share of the accrued loyalty time lost on unstake, in basis points
+
+    pub referral_bps: u16, // Cut of a referee's rewards paid to their referrer, in basis points
+
+    pub fee_bps: u16, // Protocol fee taken from every reward payout, in basis points
+    pub fee_recipient: Pubkey, // Treasury wallet receiving the protocol fee
+
+    pub deposit_fee_bps: u16, // Fee taken from every deposit, in basis points
+    pub withdraw_fee_bps: u16, // Fee taken from every withdrawal, in basis points
 
     pub paused: bool, // Is pool paused/unpaused
     pub bump: u8, // Random value to derive this pool pda
@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+
+/// Constants
+pub const POSITION_COUNTER_SEED: &str = "POSITION_COUNTER";
+
+/**
+ * Struct counting the positions a user has opened in a pool
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct PositionCounter {
+    pub owner: Pubkey, // The owner of the positions
+    pub pool: Pubkey, // The staking pool address
+
+    pub position_count: u64, // Number of positions opened, the next one gets this index
+    pub total_principal: u128, // Stake deposited into the owner's positions and not yet withdrawn, checked against `max_user_stake`
+    pub referrer: Pubkey, // Who referred the owner, recorded once and inherited by the positions opened after
+
+    pub bump: u8, // Random value to derive position counter pda
+}
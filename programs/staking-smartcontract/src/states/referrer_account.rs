@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+
+/// Constants
+pub const REFERRER_SEED: &str = "REFERRER";
+
+/**
+ * Struct holding the referral rewards earned by a referrer in a pool
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct ReferrerAccount {
+    pub referrer: Pubkey, // The referrer earning the rewards
+    pub pool: Pubkey, // The staking pool address
+
+    pub accrued_reward: u64, // Referral rewards waiting to be claimed
+    pub total_earned: u64, // All referral rewards ever earned
+
+    pub bump: u8, // Random value to derive referrer account pda
+}
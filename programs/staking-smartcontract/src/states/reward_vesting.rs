@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+
+/// Constants
+pub const REWARD_VESTING_SEED: &str = "REWARD_VESTING";
+pub const REWARD_VESTING_VAULT_SEED: &str = "REWARD_VESTING_VAULT";
+
+/**
+ * Struct holding a position's claimed rewards while they vest, one per position and reward recipient
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct RewardVesting {
+    pub owner: Pubkey, // The owner of the vesting rewards
+    pub pool: Pubkey, // The staking pool address
+    pub position: Pubkey, // The position whose rewards vest here
+
+    pub total_amount: u64, // Rewards following the current vesting schedule
+    pub released_amount: u64, // Rewards of the current schedule already moved out of it
+    pub unlocked_amount: u64, // Vested rewards carried over from earlier schedules, ready to withdraw
+
+    pub start_time: i64, // Start of the current vesting schedule
+    pub cliff_time: i64, // Nothing of the current schedule vests before this time
+    pub end_time: i64, // The current schedule is fully vested at this time
+
+    pub bump: u8, // Random value to derive reward vesting pda
+}
@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+
+/// Constants
+pub const TERM_PRODUCT_SEED: &str = "TERM_PRODUCT";
+pub const TERM_POSITION_SEED: &str = "TERM_POSITION";
+pub const TERM_VAULT_SEED: &str = "TERM_VAULT";
+pub const TERM_REWARD_VAULT_SEED: &str = "TERM_REWARD_VAULT";
+pub const MAX_TERM_APY_BPS: u32 = 10_000; // 100% APY
+
+/**
+ * Struct for a fixed-term deposit product offered by a pool
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct TermProduct {
+    pub pool: Pubkey, // The staking pool address
+    pub term_id: u16, // Id of the product within the pool
+
+    pub duration: i64, // Seconds from opening a deposit to its maturity
+    pub apy_bps: u32, // Simple interest paid over a year, prorated over the term, in basis points
+
+    pub allow_early_redeem: bool, // Can deposits be redeemed before maturity
+    pub early_redeem_penalty_bps: u16, // Share of the principal taken on early redemption, in basis points
+
+    pub total_principal: u64, // Principal of the open deposits
+    pub reserved_interest: u64, // Interest owed to the open deposits, held back in the term reward vault
+    pub position_count: u64, // Number of deposits ever opened, used to derive the next position
+
+    pub bump: u8, // Random value to derive term product pda
+}
+
+/**
+ * Struct for a single fixed-term deposit
+ */
+#[account]
+#[derive(InitSpace)]
+pub struct TermPosition {
+    pub owner: Pubkey, // The owner of this deposit
+    pub term_product: Pubkey, // The product it was opened in
+    pub position_id: u64, // Index of the deposit within the product
+
+    pub principal: u64, // Stake token deposited
+    pub interest: u64, // Reward token paid on redemption at or after maturity
+
+    pub start_time: i64, // When the deposit was opened
+    pub maturity_time: i64, // When the deposit can be redeemed with interest
+
+    pub bump: u8, // Random value to derive term position pda
+}
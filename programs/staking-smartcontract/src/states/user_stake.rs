@@ -3,6 +3,10 @@ use anchor_lang::prelude::*;
 
 /// Constants
 pub const USER_STAKE_SEED: &str = "USER_STAKE";
+pub const POSITION_MINT_SEED: &str = "POSITION_MINT";
+pub const POSITION_NFT_NAME: &str = "Staking Position"; // Followed by the position index
+pub const POSITION_NFT_SYMBOL: &str = "STAKEPOS";
+pub const POSITION_NFT_URI: &str = "";
 
 /**
  * Struct defining a user's stake
@@ -12,11 +16,34 @@ pub const USER_STAKE_SEED: &str = "USER_STAKE";
 pub struct UserStake {
     pub owner: Pubkey, // The owner of this stake
     pub pool: Pubkey, // The staking pool address
-    
+    pub position_index: u64, // Index of the position among the owner's positions in the pool
+
     pub shares: u128, // User shares
     pub reward_debt: u128, // Rewards already accounted for
+    pub unclaimed_reward: u128, // Rewards settled to the user but not paid out yet
+
+    pub weighted_shares: u128, // User shares multiplied by the lock boost
+    pub boost_bps: u16, // Lock boost, in basis points, applied until `lock_end`
+    pub lock_end: i64, // The shares can not be unstaked before this time
+
+    pub last_stake_time: i64, // Start of the user's uninterrupted staking, drives the loyalty multiplier
+
+    pub auto_compound: bool, // Has the user opted in to keeper auto-compounding
+    pub max_keeper_fee_bps: u16, // Highest keeper fee the user accepts, in basis points
+
+    pub referrer: Pubkey, // Who referred the user, default when there is no referrer
+
+    pub reward_recipient: Pubkey, // Who receives the rewards, default pays the owner
+    pub claim_delegate: Pubkey, // May claim rewards on the owner's behalf, default when there is none
+
+    pub vesting_recipient: Pubkey, // Recipient whose vesting schedule holds the position's rewards, default when none
+    pub vesting_end: i64, // That schedule has unvested rewards until this time
+
+    pub epoch_shares: u128, // Shares held for the whole of `epoch_checkpoint`
+    pub epoch_checkpoint: u64, // Epoch `epoch_shares` applies to, later epochs are eligible for all `shares`
+    pub next_claim_epoch: u64, // First epoch not claimed yet
 
-    pub last_stake_time: i64, // The last time the user changes their stake position
+    pub position_mint: Pubkey, // NFT representing the position, its holder controls it. Default when there is none
 
     pub bump: u8, // Random value to derive user stake pda
 }
\ No newline at end of file
@@ -14,4 +14,100 @@ pub enum StakingError {
     InvalidAmount,
     #[msg("Insufficient shares")]
     InsufficientShares,
-}
\ No newline at end of file
+    #[msg("Reward mint does not match stake mint")]
+    MintMismatch,
+    #[msg("Fee exceeds the allowed maximum")]
+    FeeTooHigh,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Vesting accounts are required for this pool")]
+    MissingVestingAccount,
+    #[msg("Invalid lock duration")]
+    InvalidLockDuration,
+    #[msg("Stake is still locked")]
+    StakeLocked,
+    #[msg("Invalid boost")]
+    InvalidBoost,
+    #[msg("Invalid loyalty configuration")]
+    InvalidLoyaltyConfig,
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+    #[msg("Referrer account is required for this position")]
+    MissingReferrerAccount,
+    #[msg("Treasury account is required for this pool")]
+    MissingTreasuryAccount,
+    #[msg("Fee vault is required for this pool")]
+    MissingFeeVault,
+    #[msg("Invalid emission schedule")]
+    InvalidEmissionSchedule,
+    #[msg("Invalid rewards budget")]
+    InvalidRewardBudget,
+    #[msg("Invalid reward APR")]
+    InvalidRewardApr,
+    #[msg("Invalid rate curve")]
+    InvalidRateCurve,
+    #[msg("Epoch mode is not enabled")]
+    EpochModeDisabled,
+    #[msg("Invalid epoch")]
+    InvalidEpoch,
+    #[msg("Epoch has not ended yet")]
+    EpochNotEnded,
+    #[msg("Finished epochs must be claimed first")]
+    UnclaimedEpochs,
+    #[msg("A bonus distribution is still active")]
+    BonusActive,
+    #[msg("Bonus distribution has expired")]
+    BonusExpired,
+    #[msg("Bonus distribution has not expired yet")]
+    BonusNotExpired,
+    #[msg("Bonus already claimed")]
+    BonusAlreadyClaimed,
+    #[msg("Invalid merkle proof")]
+    InvalidProof,
+    #[msg("Pool has not started yet")]
+    PoolNotStarted,
+    #[msg("Pool has already started")]
+    PoolAlreadyStarted,
+    #[msg("Invalid start time")]
+    InvalidStartTime,
+    #[msg("Deposits are not open yet")]
+    DepositsNotOpen,
+    #[msg("Deposits are closed")]
+    DepositsClosed,
+    #[msg("Deposit exceeds the pool capacity")]
+    PoolCapacityExceeded,
+    #[msg("Deposit exceeds the per-user capacity")]
+    UserCapacityExceeded,
+    #[msg("Deposit is below the minimum stake amount")]
+    StakeBelowMinimum,
+    #[msg("Invalid deposit window")]
+    InvalidDepositWindow,
+    #[msg("Invalid term product")]
+    InvalidTermProduct,
+    #[msg("Not enough term rewards funded to cover the interest")]
+    InsufficientTermRewards,
+    #[msg("Term deposit has not matured yet")]
+    TermNotMatured,
+    #[msg("Invalid position")]
+    InvalidPosition,
+    #[msg("Position is represented by an NFT")]
+    PositionIsNft,
+    #[msg("Missing position NFT account")]
+    MissingPositionNft,
+    #[msg("Position has unclaimed rewards")]
+    UnclaimedRewards,
+    #[msg("Slippage exceeded")]
+    SlippageExceeded,
+    #[msg("Transaction expired")]
+    Expired,
+    #[msg("Epoch has ended, advance it first")]
+    EpochEnded,
+    #[msg("Rewards vest in this pool")]
+    RewardsVest,
+    #[msg("Position's rewards are still vesting to another recipient")]
+    VestingLocked,
+    #[msg("Referral chain is too long to check for loops")]
+    ReferralChainTooLong,
+    #[msg("Sender's position counter is required to move shares to another wallet")]
+    MissingPositionCounter,
+}
@@ -6,6 +6,7 @@ pub struct InitializePoolEvent {
     pub pool: Pubkey,
     pub admin: Pubkey,
     pub reward_rate: u64,
+    pub start_time: i64,
 }
 
 #[event]
@@ -13,13 +14,17 @@ pub struct StakeEvent {
     pub user: Pubkey,
     pub pool: Pubkey,
     pub stake_amount: u64,
+    pub deposit_fee: u64,
+    pub lock_end: i64,
 }
 
 #[event]
 pub struct ClaimRewardEvent {
     pub pool: Pubkey,
     pub user: Pubkey,
+    pub recipient: Pubkey,
     pub reward_claimed: u64,
+    pub fee_amount: u64,
 }
 
 #[event]
@@ -27,7 +32,9 @@ pub struct UnstakeEvent {
     pub pool: Pubkey,
     pub user: Pubkey,
     pub unstaked_amount: u64,
+    pub withdraw_fee: u64,
     pub reward_amount: u128,
+    pub fee_amount: u64,
 }
 
 #[event]
@@ -41,3 +48,336 @@ pub struct SetRewardEvent {
     pub pool: Pubkey,
     pub reward_rate: u64,
 }
+
+#[event]
+pub struct CompoundEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub reward_compounded: u64,
+    pub fee_amount: u64,
+    pub shares_issued: u128,
+}
+
+#[event]
+pub struct SetAutoCompoundEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub auto_compound: bool,
+    pub max_keeper_fee_bps: u16,
+}
+
+#[event]
+pub struct SetKeeperFeeEvent {
+    pub pool: Pubkey,
+    pub keeper_fee_bps: u16,
+}
+
+#[event]
+pub struct CrankCompoundEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub keeper: Pubkey,
+    pub reward_compounded: u64,
+    pub keeper_fee: u64,
+    pub fee_amount: u64,
+    pub shares_issued: u128,
+}
+
+#[event]
+pub struct SetVestingEvent {
+    pub pool: Pubkey,
+    pub vesting_duration: i64,
+    pub vesting_cliff: i64,
+    pub forfeit_unvested_on_exit: bool,
+}
+
+#[event]
+pub struct VestingTrancheEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct WithdrawVestedEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ForfeitVestingEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SetLockConfigEvent {
+    pub pool: Pubkey,
+    pub max_lock_weeks: u16,
+    pub max_boost_bps: u16,
+}
+
+#[event]
+pub struct KickEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub weighted_shares: u128,
+}
+
+#[event]
+pub struct SetLoyaltyConfigEvent {
+    pub pool: Pubkey,
+    pub loyalty_max_bps: u16,
+    pub loyalty_period: i64,
+    pub loyalty_decay_bps: u16,
+}
+
+#[event]
+pub struct SetReferralEvent {
+    pub pool: Pubkey,
+    pub referral_bps: u16,
+}
+
+#[event]
+pub struct ReferralRewardEvent {
+    pub pool: Pubkey,
+    pub referrer: Pubkey,
+    pub referee: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ClaimReferralRewardEvent {
+    pub pool: Pubkey,
+    pub referrer: Pubkey,
+    pub reward_claimed: u64,
+}
+
+#[event]
+pub struct SetRewardRecipientEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub reward_recipient: Pubkey,
+}
+
+#[event]
+pub struct SetClaimDelegateEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub claim_delegate: Pubkey,
+}
+
+#[event]
+pub struct SetProtocolFeeEvent {
+    pub pool: Pubkey,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+}
+
+#[event]
+pub struct SetPrincipalFeesEvent {
+    pub pool: Pubkey,
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+}
+
+#[event]
+pub struct CollectFeesEvent {
+    pub pool: Pubkey,
+    pub collector: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SetEmissionScheduleEvent {
+    pub pool: Pubkey,
+    pub segment_count: u8,
+    pub halving_interval: i64,
+}
+
+#[event]
+pub struct SetRewardBudgetEvent {
+    pub pool: Pubkey,
+    pub max_total_rewards: u128,
+}
+
+#[event]
+pub struct SetRewardAprEvent {
+    pub pool: Pubkey,
+    pub reward_apr_bps: u32,
+    pub price_ratio_num: u64,
+    pub price_ratio_den: u64,
+}
+
+#[event]
+pub struct SetRateCurveEvent {
+    pub pool: Pubkey,
+    pub target_stake: u128,
+    pub base_rate: u64,
+    pub kink_bps: u16,
+    pub kink_rate: u64,
+    pub target_rate: u64,
+}
+
+#[event]
+pub struct SetEpochModeEvent {
+    pub pool: Pubkey,
+    pub epoch_duration: i64,
+}
+
+#[event]
+pub struct AdvanceEpochEvent {
+    pub pool: Pubkey,
+    pub epoch: u64,
+    pub eligible_shares: u128,
+    pub reward_amount: u64,
+}
+
+#[event]
+pub struct ClaimEpochEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub recipient: Pubkey,
+    pub epoch: u64,
+    pub reward_claimed: u64,
+    pub fee_amount: u64,
+}
+
+#[event]
+pub struct SetBonusDistributionEvent {
+    pub pool: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+    pub expiry: i64,
+}
+
+#[event]
+pub struct ClaimBonusEvent {
+    pub pool: Pubkey,
+    pub claimant: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ReclaimBonusEvent {
+    pub pool: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SetStartTimeEvent {
+    pub pool: Pubkey,
+    pub start_time: i64,
+    pub allow_pre_deposit: bool,
+}
+
+#[event]
+pub struct SetDepositLimitsEvent {
+    pub pool: Pubkey,
+    pub deposit_open: i64,
+    pub deposit_close: i64,
+    pub max_total_stake: u128,
+    pub max_user_stake: u128,
+    pub min_stake_amount: u64,
+}
+
+#[event]
+pub struct CreateTermProductEvent {
+    pub pool: Pubkey,
+    pub term_product: Pubkey,
+    pub term_id: u16,
+    pub duration: i64,
+    pub apy_bps: u32,
+    pub allow_early_redeem: bool,
+    pub early_redeem_penalty_bps: u16,
+}
+
+#[event]
+pub struct FundTermRewardsEvent {
+    pub pool: Pubkey,
+    pub term_product: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct OpenTermDepositEvent {
+    pub pool: Pubkey,
+    pub term_product: Pubkey,
+    pub term_position: Pubkey,
+    pub term_id: u16,
+    pub user: Pubkey,
+    pub principal: u64,
+    pub interest: u64,
+    pub maturity_time: i64,
+}
+
+#[event]
+pub struct RedeemTermEvent {
+    pub pool: Pubkey,
+    pub term_product: Pubkey,
+    pub term_position: Pubkey,
+    pub user: Pubkey,
+    pub principal: u64,
+    pub interest: u64,
+    pub penalty: u64,
+}
+
+#[event]
+pub struct TransferPositionEvent {
+    pub pool: Pubkey,
+    pub from: Pubkey,
+    pub from_position: u64,
+    pub to: Pubkey,
+    pub to_position: u64,
+    pub shares: u128,
+}
+
+#[event]
+pub struct SplitPositionEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub from_position: u64,
+    pub to_position: u64,
+    pub shares: u128,
+}
+
+#[event]
+pub struct MergePositionsEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub from_position: u64,
+    pub to_position: u64,
+    pub shares: u128,
+    pub unclaimed_reward: u128,
+}
+
+#[event]
+pub struct MintPositionNftEvent {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub user_stake: Pubkey,
+    pub position_mint: Pubkey,
+}
+
+#[event]
+pub struct BurnPositionNftEvent {
+    pub pool: Pubkey,
+    pub holder: Pubkey,
+    pub user_stake: Pubkey,
+    pub position_mint: Pubkey,
+}
+
+#[event]
+pub struct StakeForEvent {
+    pub pool: Pubkey,
+    pub funder: Pubkey,
+    pub beneficiary: Pubkey,
+    pub position_index: u64,
+    pub stake_amount: u64,
+    pub deposit_fee: u64,
+}
@@ -1,53 +1,689 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
 
-use crate::states::{Pool, UserStake};
-use crate::utils::StakingError;
+use crate::states::{
+    MAX_REFERRAL_DEPTH, POSITION_COUNTER_SEED, SECONDS_PER_WEEK, SECONDS_PER_YEAR, EmissionSegment, Pool, PositionCounter,
+    RewardRateMode, RewardVesting, UserStake,
+};
+use crate::utils::{mul_div, Rounding, StakingError};
 
 //------------------------------------ Helper Functions / Utils ------------------------------------//
 
-pub const SCALING_FACTOR: u128 = 1_000_000_000_000u128; // 1e12
+pub const SCALING_FACTOR: u128 = 1_000_000_000_000_000_000u128; // 1e18
+pub const BPS_DENOMINATOR: u128 = 10_000u128; // 100%
 
 /// @dev Syncs the reward variables with respect to the elapsed time since last update
-pub fn sync_reward_vars(pool: &mut Account<Pool>, now: i64) -> Result<()> {
-    if now <= pool.last_update_time {
+pub fn sync_reward_vars(pool: &mut Pool, now: i64) -> Result<()> {
+    // Nothing accrues before the pool starts
+    let accrual_start = pool.last_update_time.max(pool.start_time);
+    if now <= accrual_start {
         return Ok(());
     }
 
-    // Calculate the time passed since last update
-    let elapsed_time = (now - pool.last_update_time) as u128;
-    if pool.total_shares == 0 || pool.reward_rate == 0 {
+    // Epoch-mode pools distribute rewards per epoch instead
+    if pool.epoch_duration > 0i64 {
         pool.last_update_time = now;
         return Ok(());
     }
 
     // Calculate new rewards for the elapsed time
-    let new_rewards = (pool.reward_rate as u128)
-        .checked_mul(elapsed_time)
-        .ok_or(StakingError::Overflow)?;
+    let new_rewards = emission_for(pool, accrual_start, now)?;
+    if pool.total_weighted_shares == 0 || new_rewards == 0 {
+        pool.last_update_time = now;
+        return Ok(());
+    }
 
-    // Calculate the reward for one share and then add it increment the accumulated reward per share value
-    // reward_per_share += new_rewards * SCALING_FACTOR / total_shares
-    let prod = new_rewards.checked_mul(SCALING_FACTOR).ok_or(StakingError::Overflow)?;
-    let increment = prod.checked_div(pool.total_shares).ok_or(StakingError::Overflow)?;
+    let new_rewards = accrue_within_budget(pool, new_rewards)?;
 
-    pool.acc_reward_per_share = pool.acc_reward_per_share.checked_add(increment).ok_or(StakingError::Overflow)?;
+    distribute_reward(pool, new_rewards)?;
     pool.last_update_time = now;
 
     Ok(())
 }
 
-/// @dev Calculates the pending reward to be claimed by a user
-pub fn user_pending_reward(user_stake: &Account<UserStake>, pool: &Account<Pool>) -> Result<u128> {
-    if user_stake.shares == 0 {
-        return Ok(0u128);
+/// @dev Calculates the rewards emitted over `[from, to)` in the pool's reward rate mode
+pub fn emission_for(pool: &Pool, from: i64, to: i64) -> Result<u128> {
+    match pool.reward_rate_mode {
+        // Across every emission segment the period spans
+        RewardRateMode::Fixed => emission_between(pool, from, to),
+        RewardRateMode::Apr => apr_emission(pool, to - from),
+        RewardRateMode::Utilization => Ok((utilization_rate(pool)? as u128)
+            .checked_mul((to - from) as u128)
+            .ok_or(StakingError::Overflow)?),
+    }
+}
+
+/// @dev Records `amount` as accrued, capped so accrual stops once the emissions budget is exhausted
+/// @dev Returns the amount that may actually be distributed
+pub fn accrue_within_budget(pool: &mut Pool, amount: u128) -> Result<u128> {
+    let amount = if pool.max_total_rewards > 0u128 {
+        amount.min(pool.max_total_rewards.saturating_sub(pool.total_rewards_accrued))
+    } else {
+        amount
+    };
+
+    pool.total_rewards_accrued = pool.total_rewards_accrued.checked_add(amount).ok_or(StakingError::Overflow)?;
+
+    Ok(amount)
+}
+
+/// @dev Adds `amount` to the accumulated reward per share, together with the remainder carried from earlier
+/// @dev The part the increment can not represent is carried in `reward_remainder` instead of being lost
+pub fn distribute_reward(pool: &mut Pool, amount: u128) -> Result<()> {
+    let amount = amount.checked_add(pool.reward_remainder).ok_or(StakingError::Overflow)?;
+    if pool.total_weighted_shares == 0u128 {
+        pool.reward_remainder = amount;
+        return Ok(());
+    }
+
+    // reward_per_share += amount * SCALING_FACTOR / total_weighted_shares, rounded down
+    let increment = mul_div(amount, SCALING_FACTOR, pool.total_weighted_shares, Rounding::Down)?;
+
+    // Rounded up, so the remainder never makes the pool owe more than it accrued
+    let distributed = mul_div(increment, pool.total_weighted_shares, SCALING_FACTOR, Rounding::Up)?;
+
+    pool.reward_remainder = amount.checked_sub(distributed).ok_or(StakingError::Overflow)?;
+    pool.acc_reward_per_share = pool.acc_reward_per_share.checked_add(increment).ok_or(StakingError::Overflow)?;
+
+    Ok(())
+}
+
+/// @dev Returns the emission segment in effect at time `t`
+/// @dev A flat `reward_rate` is reported as a segment starting at 0, a halving schedule as its current period
+pub fn emission_segment_at(pool: &Pool, t: i64) -> EmissionSegment {
+    if pool.emission_segment_count > 0 {
+        // Nothing is emitted before the first segment starts
+        return pool.emission_segments[..pool.emission_segment_count as usize]
+            .iter()
+            .rev()
+            .find(|segment| segment.start_time <= t)
+            .copied()
+            .unwrap_or_default();
+    }
+
+    if pool.halving_interval > 0 && t >= pool.halving_start {
+        let halvings = (t - pool.halving_start) / pool.halving_interval;
+        return EmissionSegment {
+            start_time: pool.halving_start + halvings * pool.halving_interval,
+            rate: if halvings >= 64 { 0u64 } else { pool.reward_rate >> halvings },
+        };
+    }
+
+    EmissionSegment { start_time: 0i64, rate: pool.reward_rate }
+}
+
+/// @dev Returns when the emission segment in effect at time `t` ends, `i64::MAX` if it never does
+fn emission_segment_end(pool: &Pool, t: i64) -> i64 {
+    if pool.emission_segment_count > 0 {
+        return pool.emission_segments[..pool.emission_segment_count as usize]
+            .iter()
+            .find(|segment| segment.start_time > t)
+            .map_or(i64::MAX, |segment| segment.start_time);
+    }
+
+    if pool.halving_interval > 0 {
+        if t < pool.halving_start {
+            return pool.halving_start;
+        }
+        let halvings = (t - pool.halving_start) / pool.halving_interval;
+        return pool.halving_start.saturating_add((halvings + 1).saturating_mul(pool.halving_interval));
+    }
+
+    i64::MAX
+}
+
+/// @dev Integrates the emission schedule over `[from, to)`
+pub fn emission_between(pool: &Pool, from: i64, to: i64) -> Result<u128> {
+    let mut total = 0u128;
+    let mut t = from;
+
+    while t < to {
+        let segment = emission_segment_at(pool, t);
+
+        // A halving schedule never emits again once the rate reaches 0
+        if segment.rate == 0u64 && pool.emission_segment_count == 0 {
+            break;
+        }
+
+        let end = emission_segment_end(pool, t).min(to);
+        let emitted = (segment.rate as u128).checked_mul((end - t) as u128).ok_or(StakingError::Overflow)?;
+
+        total = total.checked_add(emitted).ok_or(StakingError::Overflow)?;
+        t = end;
+    }
+
+    Ok(total)
+}
+
+/// @dev Converts an amount of raw stake token into raw reward token at the configured price ratio
+pub fn stake_value_in_reward(pool: &Pool, amount: u128) -> Result<u128> {
+    let reward_unit = 10u128.checked_pow(pool.reward_decimals as u32).ok_or(StakingError::Overflow)?;
+    let stake_unit = 10u128.checked_pow(pool.stake_decimals as u32).ok_or(StakingError::Overflow)?;
+
+    // value = amount * num * 10^reward_decimals / (den * 10^stake_decimals)
+    let numerator = (pool.price_ratio_num as u128).checked_mul(reward_unit).ok_or(StakingError::Overflow)?;
+    let denominator = (pool.price_ratio_den as u128).checked_mul(stake_unit).ok_or(StakingError::Overflow)?;
+
+    mul_div(amount, numerator, denominator, Rounding::Down)
+}
+
+/// @dev Calculates the rewards emitted over `elapsed` seconds in `Apr` mode
+/// @dev emission = value(total_stake) * reward_apr_bps * elapsed / (BPS * SECONDS_PER_YEAR)
+pub fn apr_emission(pool: &Pool, elapsed: i64) -> Result<u128> {
+    let stake_value = stake_value_in_reward(pool, pool.total_stake)?;
+    let apr_time = (pool.reward_apr_bps as u128).checked_mul(elapsed as u128).ok_or(StakingError::Overflow)?;
+
+    mul_div(stake_value, apr_time, BPS_DENOMINATOR * SECONDS_PER_YEAR as u128, Rounding::Down)
+}
+
+/// @dev Calculates the rate of the utilization curve for the current `total_stake`
+pub fn utilization_rate(pool: &Pool) -> Result<u64> {
+    let curve = &pool.rate_curve;
+
+    // utilization = total_stake * BPS / target_stake, capped at 100%
+    let utilization = mul_div(pool.total_stake, BPS_DENOMINATOR, curve.target_stake, Rounding::Down)?.min(BPS_DENOMINATOR);
+    let kink = curve.kink_bps as u128;
+
+    let rate = if utilization <= kink {
+        interpolate(curve.base_rate, curve.kink_rate, utilization, kink)?
+    } else {
+        interpolate(curve.kink_rate, curve.target_rate, utilization - kink, BPS_DENOMINATOR - kink)?
+    };
+
+    Ok(rate.try_into().map_err(|_| StakingError::Overflow)?)
+}
+
+/// @dev Linearly interpolates from `from` to `to` at `step / steps`, rounded down
+fn interpolate(from: u64, to: u64, step: u128, steps: u128) -> Result<u128> {
+    if to >= from {
+        let delta = mul_div((to - from) as u128, step, steps, Rounding::Down)?;
+        Ok(from as u128 + delta)
+    } else {
+        let delta = mul_div((from - to) as u128, step, steps, Rounding::Up)?;
+        Ok(from as u128 - delta)
+    }
+}
+
+/// @dev Calculates the APR currently paid to stakers, in basis points
+/// @dev Outside of `Apr` mode it is derived from the current emission rate and the value of `total_stake`
+pub fn effective_apr_bps(pool: &Pool, now: i64) -> Result<u64> {
+    if pool.reward_rate_mode == RewardRateMode::Apr {
+        return Ok(pool.reward_apr_bps as u64);
+    }
+
+    let stake_value = stake_value_in_reward(pool, pool.total_stake)?;
+    if stake_value == 0u128 {
+        return Ok(0u64);
+    }
+
+    // apr = rate * SECONDS_PER_YEAR * BPS / value(total_stake)
+    let rate = match pool.reward_rate_mode {
+        RewardRateMode::Utilization => utilization_rate(pool)? as u128,
+        _ => emission_segment_at(pool, now).rate as u128,
+    };
+    let yearly = rate.checked_mul(SECONDS_PER_YEAR as u128).ok_or(StakingError::Overflow)?;
+    let apr = mul_div(yearly, BPS_DENOMINATOR, stake_value, Rounding::Down)?;
+
+    Ok(apr.min(u64::MAX as u128) as u64)
+}
+
+/// @dev Calculates the interest in reward token earned by `principal` stake token over `duration` seconds
+/// @dev interest = value(principal) * apy_bps * duration / (BPS * SECONDS_PER_YEAR)
+pub fn term_interest(pool: &Pool, principal: u64, apy_bps: u32, duration: i64) -> Result<u64> {
+    let principal_value = stake_value_in_reward(pool, principal as u128)?;
+    let apy_time = (apy_bps as u128).checked_mul(duration as u128).ok_or(StakingError::Overflow)?;
+    let interest = mul_div(principal_value, apy_time, BPS_DENOMINATOR * SECONDS_PER_YEAR as u128, Rounding::Down)?;
+
+    Ok(interest.try_into().map_err(|_| StakingError::Overflow)?)
+}
+
+/// @dev Records a reward settled out of the accumulator, capped so payouts never exceed the emissions budget
+/// @dev Returns the amount that may actually be paid
+pub fn take_reward_budget(pool: &mut Pool, reward: u128) -> Result<u128> {
+    let reward = if pool.max_total_rewards > 0u128 {
+        reward.min(pool.max_total_rewards.saturating_sub(pool.total_rewards_paid))
+    } else {
+        reward
+    };
+
+    pool.total_rewards_paid = pool.total_rewards_paid.checked_add(reward).ok_or(StakingError::Overflow)?;
+
+    Ok(reward)
+}
+
+/// @dev Calculates the shares issued for depositing `amount` at the current share rate
+pub fn calculate_shares(pool: &Pool, amount: u128) -> Result<u128> {
+    if pool.total_shares == 0 || pool.total_stake == 0 {
+        return Ok(amount);
+    }
+
+    // shares = amount * total_shares / total_stake, rounded down
+    mul_div(amount, pool.total_shares, pool.total_stake, Rounding::Down)
+}
+
+/// @dev Returns the stake `shares` are worth at the current share rate, rounded down
+pub fn stake_value_of(pool: &Pool, shares: u128) -> Result<u128> {
+    if pool.total_shares == 0 {
+        return Ok(0);
+    }
+
+    // amount = shares * total_stake / total_shares
+    mul_div(shares, pool.total_stake, pool.total_shares, Rounding::Down)
+}
+
+/// @dev Checks the pool's stake after a deposit against `max_total_stake`
+pub fn check_pool_capacity(pool: &Pool, total_stake_after: u128) -> Result<()> {
+    require!(pool.max_total_stake == 0u128 || total_stake_after <= pool.max_total_stake, StakingError::PoolCapacityExceeded);
+
+    Ok(())
+}
+
+/// @dev Checks a wallet's principal across all its positions after a deposit against `max_user_stake`
+pub fn check_user_capacity(pool: &Pool, total_principal_after: u128) -> Result<()> {
+    require!(pool.max_user_stake == 0u128 || total_principal_after <= pool.max_user_stake, StakingError::UserCapacityExceeded);
+
+    Ok(())
+}
+
+/// @dev Quotes an unstake of `shares` -- the stake they are worth, rounded down, and the withdrawal fee on it
+pub fn quote_unstake_shares(pool: &Pool, shares: u128) -> Result<(u128, u64)> {
+    // amount = shares * total_stake / total_shares
+    let amount = mul_div(shares, pool.total_stake, pool.total_shares, Rounding::Down)?;
+    let withdraw_fee: u64 = bps_of(amount, pool.withdraw_fee_bps)?.try_into().map_err(|_| StakingError::Overflow)?;
+
+    Ok((amount, withdraw_fee))
+}
+
+/// @dev Quotes an unstake paying out exactly `amount` after the withdrawal fee -- the shares to burn, rounded up,
+/// the stake they stand for and the withdrawal fee
+pub fn quote_unstake_amount(pool: &Pool, amount: u64) -> Result<(u128, u128, u64)> {
+    // gross = amount / (1 - withdraw_fee), rounded up so the fee is never undercharged
+    let fee_free_bps = BPS_DENOMINATOR.checked_sub(pool.withdraw_fee_bps as u128).ok_or(StakingError::Overflow)?;
+    let gross_amount = mul_div(amount as u128, BPS_DENOMINATOR, fee_free_bps, Rounding::Up)?;
+    let withdraw_fee: u64 = (gross_amount - amount as u128).try_into().map_err(|_| StakingError::Overflow)?;
+
+    // shares = gross * total_shares / total_stake, rounded up in favour of the pool
+    let shares = mul_div(gross_amount, pool.total_shares, pool.total_stake, Rounding::Up)?;
+
+    Ok((shares, gross_amount, withdraw_fee))
+}
+
+/// @dev Calculates the pending reward to be claimed by a user, including rewards settled earlier
+pub fn user_pending_reward(user_stake: &UserStake, pool: &Pool) -> Result<u128> {
+    // Rounded down, the user is never paid more than the pool accrued for them
+    let acc_reward = mul_div(user_stake.weighted_shares, pool.acc_reward_per_share, SCALING_FACTOR, Rounding::Down)?;
+
+    let accrued = acc_reward.saturating_sub(user_stake.reward_debt);
+
+    Ok(accrued.checked_add(user_stake.unclaimed_reward).ok_or(StakingError::Overflow)?)
+}
+
+/// @dev Calculates the reward boost for a lock with `lock_remaining` seconds left, in basis points
+/// @dev Ramps linearly from 1x for no lock to `max_boost_bps` for a lock of `max_lock_weeks`
+pub fn calculate_boost(pool: &Pool, lock_remaining: i64) -> Result<u16> {
+    if pool.max_lock_weeks == 0 || lock_remaining <= 0 {
+        return Ok(BPS_DENOMINATOR as u16);
     }
 
-    let prod = user_stake.shares.checked_mul(pool.acc_reward_per_share).ok_or(StakingError::Overflow)?;
-    let acc_reward = prod.checked_div(SCALING_FACTOR).ok_or(StakingError::Overflow)?;
+    let max_lock = (pool.max_lock_weeks as u128).checked_mul(SECONDS_PER_WEEK as u128).ok_or(StakingError::Overflow)?;
+    let remaining = (lock_remaining as u128).min(max_lock);
+
+    // boost = 1x + (max_boost - 1x) * remaining / max_lock
+    let extra_bps = (pool.max_boost_bps as u128).saturating_sub(BPS_DENOMINATOR);
+    let prod = extra_bps.checked_mul(remaining).ok_or(StakingError::Overflow)?;
+    let boost = BPS_DENOMINATOR.checked_add(prod / max_lock).ok_or(StakingError::Overflow)?;
 
-    if acc_reward <= user_stake.reward_debt {
-        return Ok(0u128);
+    Ok(boost.try_into().map_err(|_| StakingError::Overflow)?)
+}
+
+/// @dev Calculates the loyalty multiplier of a position, in basis points
+/// @dev Ramps linearly from 1x to `loyalty_max_bps` over `loyalty_period` seconds since `last_stake_time`
+pub fn calculate_loyalty(pool: &Pool, user_stake: &UserStake, now: i64) -> Result<u16> {
+    if pool.loyalty_max_bps as u128 <= BPS_DENOMINATOR || pool.loyalty_period <= 0 || user_stake.shares == 0 {
+        return Ok(BPS_DENOMINATOR as u16);
     }
 
-    Ok(acc_reward.checked_sub(user_stake.reward_debt).ok_or(StakingError::Overflow)?)
-}
\ No newline at end of file
+    let staked_for = (now - user_stake.last_stake_time).clamp(0, pool.loyalty_period) as u128;
+
+    // loyalty = 1x + (loyalty_max - 1x) * staked_for / loyalty_period
+    let extra_bps = pool.loyalty_max_bps as u128 - BPS_DENOMINATOR;
+    let prod = extra_bps.checked_mul(staked_for).ok_or(StakingError::Overflow)?;
+    let loyalty = BPS_DENOMINATOR.checked_add(prod / pool.loyalty_period as u128).ok_or(StakingError::Overflow)?;
+
+    Ok(loyalty.try_into().map_err(|_| StakingError::Overflow)?)
+}
+
+/// @dev Returns the start of a position's uninterrupted staking once `added_shares` are deposited into it at `now`
+/// @dev The new shares start their loyalty period now, so the staking time the position has built up, capped at
+/// `loyalty_period`, is averaged over the old and new shares
+pub fn blended_stake_time(pool: &Pool, user_stake: &UserStake, added_shares: u128, now: i64) -> Result<i64> {
+    let total_shares = user_stake.shares.checked_add(added_shares).ok_or(StakingError::Overflow)?;
+    if total_shares == 0u128 {
+        return Ok(now);
+    }
+
+    let staked_for = (now - user_stake.last_stake_time).clamp(0, pool.loyalty_period.max(0)) as u128;
+
+    // Rounded down, so the new shares never take over more loyalty than the old ones had
+    let kept: i64 = mul_div(staked_for, user_stake.shares, total_shares, Rounding::Down)?
+        .try_into()
+        .map_err(|_| StakingError::Overflow)?;
+
+    Ok(now - kept)
+}
+
+/// @dev Recomputes the user's reward weight from their shares, lock and loyalty, and resets the reward debt
+/// @dev Pending rewards must be paid out or moved into `unclaimed_reward` beforehand
+pub fn update_user_weight(pool: &mut Pool, user_stake: &mut UserStake, now: i64) -> Result<()> {
+    let boost_bps = if user_stake.lock_end > now { user_stake.boost_bps } else { BPS_DENOMINATOR as u16 };
+    let loyalty_bps = calculate_loyalty(pool, user_stake, now)?;
+
+    // weighted_shares = shares * boost * loyalty
+    let weighted_shares = user_stake.shares
+        .checked_mul(boost_bps as u128)
+        .and_then(|v| v.checked_mul(loyalty_bps as u128))
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR * BPS_DENOMINATOR))
+        .ok_or(StakingError::Overflow)?;
+
+    pool.total_weighted_shares = pool.total_weighted_shares
+        .checked_sub(user_stake.weighted_shares)
+        .and_then(|total| total.checked_add(weighted_shares))
+        .ok_or(StakingError::Overflow)?;
+    user_stake.weighted_shares = weighted_shares;
+
+    // Set new reward_debt = user.weighted_shares * reward_per_share / SCALING, rounded up in favour of the pool
+    user_stake.reward_debt = mul_div(weighted_shares, pool.acc_reward_per_share, SCALING_FACTOR, Rounding::Up)?;
+
+    Ok(())
+}
+
+/// @dev Returns whether an epoch-mode position still has finished epochs to claim
+pub fn has_unclaimed_epochs(pool: &Pool, user_stake: &UserStake) -> bool {
+    pool.epoch_duration > 0i64 && user_stake.shares > 0u128 && user_stake.next_claim_epoch.max(1) < pool.current_epoch
+}
+
+/// @dev Returns whether the epoch in progress has ended and is waiting on `advance_epoch`
+pub fn epoch_ended(pool: &Pool, now: i64) -> bool {
+    pool.epoch_duration > 0i64 && now >= pool.epoch_start.saturating_add(pool.epoch_duration)
+}
+
+/// @dev Brings the position's epoch eligibility up to the epoch in progress, before its shares change
+/// @dev Finished epochs must be claimed first, as their eligibility is derived from the unchanged shares
+/// @dev Shares are frozen between the end of an epoch and `advance_epoch`, which snapshots them for the next epoch
+pub fn checkpoint_epoch(pool: &Pool, user_stake: &mut UserStake, now: i64) -> Result<()> {
+    if pool.epoch_duration == 0i64 {
+        return Ok(());
+    }
+    require!(!epoch_ended(pool, now), StakingError::EpochEnded);
+    require!(!has_unclaimed_epochs(pool, user_stake), StakingError::UnclaimedEpochs);
+
+    if user_stake.epoch_checkpoint < pool.current_epoch {
+        user_stake.epoch_shares = user_stake.shares;
+        user_stake.epoch_checkpoint = pool.current_epoch;
+    }
+    user_stake.next_claim_epoch = user_stake.next_claim_epoch.max(pool.current_epoch);
+
+    Ok(())
+}
+
+/// @dev Caps the position's eligibility for the epoch in progress at its remaining shares, after an unstake
+pub fn reduce_epoch_shares(pool: &mut Pool, user_stake: &mut UserStake) -> Result<()> {
+    if pool.epoch_duration == 0i64 || user_stake.epoch_shares <= user_stake.shares {
+        return Ok(());
+    }
+
+    let removed = user_stake.epoch_shares - user_stake.shares;
+    pool.epoch_eligible_shares = pool.epoch_eligible_shares.checked_sub(removed).ok_or(StakingError::Overflow)?;
+    user_stake.epoch_shares = user_stake.shares;
+
+    Ok(())
+}
+
+/// @dev Returns the shares the position held for the whole of a finished `epoch` it has not claimed yet
+pub fn epoch_shares_of(user_stake: &UserStake, epoch: u64) -> u128 {
+    if epoch == user_stake.epoch_checkpoint {
+        user_stake.epoch_shares
+    } else {
+        user_stake.shares
+    }
+}
+
+/// @dev Sets up a position created at `position_index`, or checks that an existing one belongs to `owner`
+/// @dev Positions are opened in order, a new one must take the owner's next index
+pub fn open_position(
+    position_counter: &mut PositionCounter,
+    counter_bump: u8,
+    user_stake: &mut UserStake,
+    stake_bump: u8,
+    owner: Pubkey,
+    pool: Pubkey,
+    position_index: u64,
+) -> Result<()> {
+    if position_counter.owner == Pubkey::default() {
+        position_counter.owner = owner;
+        position_counter.pool = pool;
+        position_counter.bump = counter_bump;
+    }
+    require!(position_index <= position_counter.position_count, StakingError::InvalidPosition);
+
+    if user_stake.owner == Pubkey::default() {
+        user_stake.owner = owner;
+        user_stake.pool = pool;
+        user_stake.position_index = position_index;
+        user_stake.referrer = position_counter.referrer;
+        user_stake.bump = stake_bump;
+
+        // Indexes of closed positions can be reopened without counting them again
+        if position_index == position_counter.position_count {
+            position_counter.position_count = position_counter.position_count.checked_add(1u64).ok_or(StakingError::Overflow)?;
+        }
+    } else {
+        require!(user_stake.owner == owner, StakingError::InvalidOwner);
+        require!(user_stake.pool == pool, StakingError::InvalidPool);
+    }
+
+    Ok(())
+}
+
+/// @dev Moves `shares` from one position to another, settling both positions' pending rewards first
+/// @dev The destination takes over the later of both locks, and the loyalty of the source when it was empty.
+/// The reward vars must be synced beforehand
+pub fn move_shares(pool: &mut Pool, from: &mut UserStake, to: &mut UserStake, shares: u128, now: i64) -> Result<()> {
+    require!(shares > 0u128 && shares <= from.shares, StakingError::InsufficientShares);
+
+    // Settle the pending rewards before the weights change
+    from.unclaimed_reward = user_pending_reward(from, pool)?;
+    to.unclaimed_reward = user_pending_reward(to, pool)?;
+
+    checkpoint_epoch(pool, from, now)?;
+    checkpoint_epoch(pool, to, now)?;
+
+    to.last_stake_time = if to.shares == 0u128 { from.last_stake_time } else { to.last_stake_time.max(from.last_stake_time) };
+
+    from.shares -= shares;
+    to.shares = to.shares.checked_add(shares).ok_or(StakingError::Overflow)?;
+
+    // The moved shares keep their eligibility for the epoch in progress
+    if pool.epoch_duration > 0i64 && from.epoch_shares > from.shares {
+        let moved = from.epoch_shares - from.shares;
+        from.epoch_shares = from.shares;
+        to.epoch_shares = to.epoch_shares.checked_add(moved).ok_or(StakingError::Overflow)?;
+    }
+
+    // The shares stay locked for as long as they were
+    to.lock_end = to.lock_end.max(from.lock_end);
+    to.boost_bps = calculate_boost(pool, to.lock_end - now)?;
+
+    update_user_weight(pool, from, now)?;
+    update_user_weight(pool, to, now)?;
+
+    Ok(())
+}
+
+/// @dev Returns whether `signer` controls the position -- the holder of its NFT when it has one, else the owner
+pub fn is_position_holder(user_stake: &UserStake, signer: &Pubkey, position_nft: Option<&TokenAccount>) -> bool {
+    if user_stake.position_mint == Pubkey::default() {
+        return user_stake.owner == *signer;
+    }
+
+    position_nft.is_some_and(|nft| nft.mint == user_stake.position_mint && nft.owner == *signer && nft.amount == 1u64)
+}
+
+/// @dev Returns whether `signer` may claim the position's rewards -- its holder, or the claim delegate without an NFT
+pub fn can_claim_position(user_stake: &UserStake, signer: &Pubkey, position_nft: Option<&TokenAccount>) -> bool {
+    is_position_holder(user_stake, signer, position_nft)
+        || (user_stake.position_mint == Pubkey::default() && user_stake.claim_delegate == *signer)
+}
+
+/// @dev Returns who receives the rewards claimed by `signer` -- the NFT holder for NFT positions
+pub fn reward_recipient_for(user_stake: &UserStake, signer: &Pubkey) -> Pubkey {
+    if user_stake.position_mint == Pubkey::default() {
+        reward_recipient_of(user_stake)
+    } else {
+        *signer
+    }
+}
+
+/// @dev Rejects paying the position's rewards to `recipient` while they still vest to another recipient, so a full
+/// exit always finds the one schedule to forfeit
+pub fn check_vesting_recipient(user_stake: &UserStake, recipient: &Pubkey, now: i64) -> Result<()> {
+    require!(
+        user_stake.vesting_recipient == Pubkey::default()
+            || user_stake.vesting_recipient == *recipient
+            || now >= user_stake.vesting_end,
+        StakingError::VestingLocked
+    );
+
+    Ok(())
+}
+
+/// @dev Rejects recording `referrer` for `user` when it closes a referral loop -- walks up the chain of referrers
+/// through their position counters in `counters`, starting with the referrer's, until a wallet nobody referred
+/// @dev Fails when the chain reaches `user`, or runs longer than `MAX_REFERRAL_DEPTH`
+pub fn check_referral_chain<'a, 'info: 'a>(
+    mut counters: impl Iterator<Item = &'a AccountInfo<'info>>,
+    referrer: Pubkey,
+    user: &Pubkey,
+    pool: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let mut ancestor = referrer;
+
+    for _ in 0..MAX_REFERRAL_DEPTH {
+        require!(ancestor != *user, StakingError::InvalidReferrer);
+
+        let counter = counters.next().ok_or(StakingError::InvalidReferrer)?;
+        let (counter_key, _) = Pubkey::find_program_address(
+            &[POSITION_COUNTER_SEED.as_bytes(), pool.as_ref(), ancestor.as_ref()],
+            program_id,
+        );
+        require!(counter.key() == counter_key, StakingError::InvalidReferrer);
+
+        // A wallet without positions has no referrer either
+        if counter.owner != program_id || counter.data_is_empty() {
+            return Ok(());
+        }
+
+        let positions = PositionCounter::try_deserialize(&mut &counter.try_borrow_data()?[..])?;
+        if positions.referrer == Pubkey::default() {
+            return Ok(());
+        }
+
+        ancestor = positions.referrer;
+    }
+
+    err!(StakingError::ReferralChainTooLong)
+}
+
+/// @dev Rejects a user instruction executed after its `deadline`, when one is given
+pub fn check_deadline(deadline: Option<i64>) -> Result<()> {
+    if let Some(deadline) = deadline {
+        require!(Clock::get()?.unix_timestamp <= deadline, StakingError::Expired);
+    }
+
+    Ok(())
+}
+
+/// @dev Returns who receives the rewards of a position -- the owner unless redirected
+pub fn reward_recipient_of(user_stake: &UserStake) -> Pubkey {
+    if user_stake.reward_recipient == Pubkey::default() {
+        user_stake.owner
+    } else {
+        user_stake.reward_recipient
+    }
+}
+
+/// @dev Calculates `bps` basis points of `amount`, rounded down
+pub fn bps_of(amount: u128, bps: u16) -> Result<u128> {
+    let prod = amount.checked_mul(bps as u128).ok_or(StakingError::Overflow)?;
+    Ok(prod.checked_div(BPS_DENOMINATOR).ok_or(StakingError::Overflow)?)
+}
+
+/// @dev Calculates how much of the current vesting schedule has vested at `now`
+pub fn vested_amount(vesting: &RewardVesting, now: i64) -> Result<u64> {
+    if now < vesting.cliff_time {
+        return Ok(0u64);
+    }
+    if now >= vesting.end_time {
+        return Ok(vesting.total_amount);
+    }
+
+    // vested = total_amount * (now - start_time) / (end_time - start_time)
+    let elapsed = (now - vesting.start_time) as u128;
+    let duration = (vesting.end_time - vesting.start_time) as u128;
+    let prod = (vesting.total_amount as u128).checked_mul(elapsed).ok_or(StakingError::Overflow)?;
+    let vested = prod.checked_div(duration).ok_or(StakingError::Overflow)?;
+
+    Ok(vested.try_into().map_err(|_| StakingError::Overflow)?)
+}
+
+/// @dev Moves everything vested so far out of the current schedule and into `unlocked_amount`
+pub fn settle_vesting(vesting: &mut RewardVesting, now: i64) -> Result<()> {
+    let vested = vested_amount(vesting, now)?;
+    let newly_vested = vested.checked_sub(vesting.released_amount).ok_or(StakingError::Overflow)?;
+
+    vesting.unlocked_amount = vesting.unlocked_amount.checked_add(newly_vested).ok_or(StakingError::Overflow)?;
+    vesting.released_amount = vested;
+
+    Ok(())
+}
+
+/// @dev Adds a tranche of `amount` to the vesting schedule
+/// @dev The still-locked remainder is merged with the new tranche, and the merged schedule
+/// ends (and clears its cliff) at the amount-weighted average of both
+pub fn add_vesting_tranche(vesting: &mut RewardVesting, amount: u64, now: i64, duration: i64, cliff: i64) -> Result<()> {
+    settle_vesting(vesting, now)?;
+
+    let locked = vesting.total_amount.checked_sub(vesting.released_amount).ok_or(StakingError::Overflow)?;
+    let new_end_time = now.checked_add(duration).ok_or(StakingError::Overflow)?;
+    let new_cliff_time = now.checked_add(cliff).ok_or(StakingError::Overflow)?;
+
+    let (end_time, cliff_time) = if locked == 0u64 {
+        (new_end_time, new_cliff_time)
+    } else {
+        let total = (locked as u128).checked_add(amount as u128).ok_or(StakingError::Overflow)?;
+        let weighted_average = |old: i64, new: i64| -> Result<i64> {
+            // Both times are >= now, so only the offsets from now are averaged
+            let old_offset = (old.max(now) - now) as u128;
+            let new_offset = (new - now) as u128;
+            let prod = old_offset
+                .checked_mul(locked as u128)
+                .and_then(|v| v.checked_add(new_offset.checked_mul(amount as u128)?))
+                .ok_or(StakingError::Overflow)?;
+            let offset: i64 = (prod / total).try_into().map_err(|_| StakingError::Overflow)?;
+            Ok(now + offset)
+        };
+        (
+            weighted_average(vesting.end_time, new_end_time)?,
+            weighted_average(vesting.cliff_time, new_cliff_time)?,
+        )
+    };
+
+    vesting.total_amount = locked.checked_add(amount).ok_or(StakingError::Overflow)?;
+    vesting.released_amount = 0u64;
+    vesting.start_time = now;
+    vesting.cliff_time = cliff_time;
+    vesting.end_time = end_time;
+
+    Ok(())
+}
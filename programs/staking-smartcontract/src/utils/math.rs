@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+
+use crate::utils::StakingError;
+
+//------------------------------------ Fixed-Point Math ------------------------------------//
+
+/// Direction in which a fixed-point result is rounded
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down, // Towards zero, used for amounts owed to a user
+    Up, // Away from zero, used for amounts owed to the pool
+}
+
+/// @dev Calculates `a * b / denominator` through a 256-bit intermediate, so the product can not overflow
+/// @dev Fails only if the denominator is 0 or the result does not fit in a `u128`
+pub fn mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> Result<u128> {
+    require!(denominator > 0u128, StakingError::Overflow);
+
+    let (quotient, remainder) = match a.checked_mul(b) {
+        Some(prod) => (prod / denominator, prod % denominator),
+        None => {
+            let (hi, lo) = full_mul(a, b);
+            require!(hi < denominator, StakingError::Overflow);
+            div_rem_wide(hi, lo, denominator)
+        }
+    };
+
+    if rounding == Rounding::Up && remainder > 0u128 {
+        return Ok(quotient.checked_add(1u128).ok_or(StakingError::Overflow)?);
+    }
+
+    Ok(quotient)
+}
+
+/// @dev Multiplies two `u128` into a 256-bit `(hi, lo)` pair
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    const MASK: u128 = u64::MAX as u128;
+
+    let (a_hi, a_lo) = (a >> 64, a & MASK);
+    let (b_hi, b_lo) = (b >> 64, b & MASK);
+
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let hi_hi = a_hi * b_hi;
+
+    // The middle terms can carry at most once past 128 bits
+    let (cross, carry_a) = hi_lo.overflowing_add(lo_hi);
+    let (cross, carry_b) = cross.overflowing_add(lo_lo >> 64);
+    let carry = (carry_a as u128 + carry_b as u128) << 64;
+
+    let lo = (cross << 64) | (lo_lo & MASK);
+    let hi = hi_hi + (cross >> 64) + carry;
+
+    (hi, lo)
+}
+
+/// @dev Divides the 256-bit `(hi, lo)` by `denominator`, returning the quotient and remainder
+/// @dev Requires `hi < denominator` so the quotient fits in a `u128`
+fn div_rem_wide(hi: u128, lo: u128, denominator: u128) -> (u128, u128) {
+    let mut remainder = hi;
+    let mut quotient = 0u128;
+
+    // Binary long division over the low 128 bits
+    for i in (0..128).rev() {
+        let overflow = remainder >> 127 == 1;
+        remainder = (remainder << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+
+        if overflow || remainder >= denominator {
+            remainder = remainder.wrapping_sub(denominator);
+            quotient |= 1;
+        }
+    }
+
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_mul_spans_256_bits() {
+        assert_eq!(full_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+        assert_eq!(full_mul(1u128 << 64, 1u128 << 64), (1, 0));
+        assert_eq!(full_mul(u128::MAX, 2), (1, u128::MAX - 1));
+    }
+
+    #[test]
+    fn div_rem_wide_divides_256_bits() {
+        assert_eq!(div_rem_wide(1, 0, 2), (1u128 << 127, 0));
+        assert_eq!(div_rem_wide(1, 5, 3), (113_427_455_640_312_821_154_458_202_477_256_070_487, 0));
+        assert_eq!(div_rem_wide(0, 7, 2), (3, 1));
+    }
+
+    #[test]
+    fn mul_div_survives_intermediate_overflow() {
+        // 1e30 * 1e30 does not fit in a u128, the result does
+        let e30 = 10u128.pow(30);
+        assert_eq!(mul_div(e30, e30, e30, Rounding::Down).unwrap(), e30);
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down).unwrap(), u128::MAX);
+
+        // Results past a u128 fail instead of wrapping
+        assert!(mul_div(u128::MAX, 2, 1, Rounding::Down).is_err());
+        assert!(mul_div(u128::MAX, u128::MAX, u128::MAX - 1, Rounding::Down).is_err());
+    }
+
+    #[test]
+    fn mul_div_rounds_up_only_inexact_results() {
+        assert_eq!(mul_div(10, 3, 5, Rounding::Down).unwrap(), 6);
+        assert_eq!(mul_div(10, 3, 5, Rounding::Up).unwrap(), 6);
+
+        assert_eq!(mul_div(10, 3, 4, Rounding::Down).unwrap(), 7);
+        assert_eq!(mul_div(10, 3, 4, Rounding::Up).unwrap(), 8);
+
+        // The same through the 256-bit path
+        let exact = 226_854_911_280_625_642_308_916_404_954_512_140_970;
+        assert_eq!(mul_div(u128::MAX, 2, 3, Rounding::Down).unwrap(), exact);
+        assert_eq!(mul_div(u128::MAX, 2, 3, Rounding::Up).unwrap(), exact);
+
+        let inexact = 97_223_533_405_982_418_132_392_744_980_505_203_272;
+        assert_eq!(mul_div(u128::MAX, 2, 7, Rounding::Down).unwrap(), inexact);
+        assert_eq!(mul_div(u128::MAX, 2, 7, Rounding::Up).unwrap(), inexact + 1);
+    }
+
+    #[test]
+    fn mul_div_rejects_zero_denominator() {
+        assert!(mul_div(1, 1, 0, Rounding::Down).is_err());
+        assert!(mul_div(u128::MAX, u128::MAX, 0, Rounding::Up).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use solana_sha256_hasher::hashv;
+
+//------------------------------------ Merkle Proofs ------------------------------------//
+
+// Domain separators, so a leaf can never be passed off as an inner node
+const LEAF_PREFIX: &[u8] = &[0u8];
+const NODE_PREFIX: &[u8] = &[1u8];
+
+/// @dev Hashes the leaf granting `amount` to `claimant` at `index` of a distribution
+pub fn leaf_hash(index: u32, claimant: &[u8], amount: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, &index.to_le_bytes(), claimant, &amount.to_le_bytes()]).to_bytes()
+}
+
+/// @dev Hashes two nodes in sorted order, so proofs do not need to encode the side of each sibling
+fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// @dev Verifies that `leaf` is part of the tree with the given `root`
+pub fn verify_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    proof.iter().fold(leaf, |node, sibling| node_hash(&node, sibling)) == *root
+}
+
+/**
+ * Off-chain builder for distribution trees and their proofs
+ * A node without a sibling is carried up to the next level unchanged
+ */
+#[cfg(not(target_os = "solana"))]
+pub struct MerkleTree {
+    levels: Vec<Vec<[u8; 32]>>, // Leaves first, root last
+}
+
+#[cfg(not(target_os = "solana"))]
+impl MerkleTree {
+    /// @dev Builds the tree over the given leaf hashes, see `leaf_hash`
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        assert!(!leaves.is_empty(), "a merkle tree needs at least one leaf");
+
+        let mut levels = vec![leaves];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Self { levels }
+    }
+
+    /// @dev Returns the root of the tree
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// @dev Returns the proof for the leaf at `index`
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+
+        proof
+    }
+}
@@ -5,4 +5,10 @@ pub mod events;
 pub use events::*;
 
 pub mod helper;
-pub use helper::*;
\ No newline at end of file
+pub use helper::*;
+
+pub mod math;
+pub use math::*;
+
+pub mod merkle;
+pub use merkle::*;
\ No newline at end of file
@@ -1,8 +1,11 @@
 use std::{path::PathBuf};
-use litesvm::LiteSVM;
-use litesvm_token::{CreateMint, spl_token};
+use litesvm::{LiteSVM, types::{FailedTransactionMetadata, TransactionMetadata}};
+use litesvm_token::{
+    CreateAssociatedTokenAccount, CreateMint, MintTo, SetAuthority, spl_token, spl_token::instruction::AuthorityType,
+};
 use sha2::{Digest, Sha256};
 use solana_sdk::{
+    clock::Clock,
     message::{AccountMeta, Instruction}, 
     pubkey::Pubkey, 
     signature::{Keypair, Signer, read_keypair_file}, 
@@ -10,11 +13,43 @@ use solana_sdk::{
 };
 use borsh::BorshDeserialize;
 use solana_system_interface::program::ID;
+use staking_smartcontract::utils::{MerkleTree, leaf_hash};
 
 
 //************************* DECLARATIONS *************************//
 
 const POOL_SEED: &str = "POOL";
+const BONUS_DISTRIBUTION_SEED: &str = "BONUS_DISTRIBUTION";
+const BONUS_VAULT_SEED: &str = "BONUS_VAULT";
+const USER_STAKE_SEED: &str = "USER_STAKE";
+const POSITION_COUNTER_SEED: &str = "POSITION_COUNTER";
+const FEE_VAULT_SEED: &str = "FEE_VAULT";
+const REWARD_VESTING_SEED: &str = "REWARD_VESTING";
+const REWARD_VESTING_VAULT_SEED: &str = "REWARD_VESTING_VAULT";
+const EPOCH_RECORD_SEED: &str = "EPOCH_RECORD";
+const TERM_PRODUCT_SEED: &str = "TERM_PRODUCT";
+const TERM_POSITION_SEED: &str = "TERM_POSITION";
+const TERM_VAULT_SEED: &str = "TERM_VAULT";
+const TERM_REWARD_VAULT_SEED: &str = "TERM_REWARD_VAULT";
+const POSITION_MINT_SEED: &str = "POSITION_MINT";
+const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const START_TIME: i64 = 1_700_000_000;
+
+#[derive(Debug, BorshDeserialize)]
+pub struct EmissionSegment {
+    pub start_time: i64,
+    pub rate: u64,
+}
+
+#[derive(Debug, BorshDeserialize)]
+pub struct RateCurve {
+    pub target_stake: u128,
+    pub base_rate: u64,
+    pub kink_bps: u16,
+    pub kink_rate: u64,
+    pub target_rate: u64,
+}
 
 #[derive(Debug, BorshDeserialize)]
 pub struct Pool {
@@ -22,15 +57,82 @@ pub struct Pool {
     pub stake_mint: Pubkey,
     pub reward_mint: Pubkey,
     pub stake_vault: Pubkey,
+    pub stake_decimals: u8,
+    pub reward_decimals: u8,
     pub reward_rate: u64,
     pub total_stake: u128,
     pub total_shares: u128,
+    pub total_weighted_shares: u128,
     pub acc_reward_per_share: u128,
+    pub reward_remainder: u128,
     pub last_update_time: i64,
+    pub start_time: i64,
+    pub allow_pre_deposit: bool,
+    pub deposit_open: i64,
+    pub deposit_close: i64,
+    pub max_total_stake: u128,
+    pub max_user_stake: u128,
+    pub min_stake_amount: u64,
+    pub emission_segments: [EmissionSegment; 8],
+    pub emission_segment_count: u8,
+    pub halving_interval: i64,
+    pub halving_start: i64,
+    pub max_total_rewards: u128,
+    pub total_rewards_accrued: u128,
+    pub total_rewards_paid: u128,
+    pub reward_rate_mode: u8,
+    pub reward_apr_bps: u32,
+    pub price_ratio_num: u64,
+    pub price_ratio_den: u64,
+    pub rate_curve: RateCurve,
+    pub epoch_duration: i64,
+    pub current_epoch: u64,
+    pub epoch_start: i64,
+    pub epoch_eligible_shares: u128,
+    pub keeper_fee_bps: u16,
+    pub vesting_duration: i64,
+    pub vesting_cliff: i64,
+    pub forfeit_unvested_on_exit: bool,
+    pub max_lock_weeks: u16,
+    pub max_boost_bps: u16,
+    pub loyalty_max_bps: u16,
+    pub loyalty_period: i64,
+    pub loyalty_decay_bps: u16,
+    pub referral_bps: u16,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
     pub paused: bool,
     pub bump: u8,
 }
 
+#[derive(Debug, BorshDeserialize)]
+pub struct UserStake {
+    pub owner: Pubkey,
+    pub pool: Pubkey,
+    pub position_index: u64,
+    pub shares: u128,
+    pub reward_debt: u128,
+    pub unclaimed_reward: u128,
+    pub weighted_shares: u128,
+    pub boost_bps: u16,
+    pub lock_end: i64,
+    pub last_stake_time: i64,
+    pub auto_compound: bool,
+    pub max_keeper_fee_bps: u16,
+    pub referrer: Pubkey,
+    pub reward_recipient: Pubkey,
+    pub claim_delegate: Pubkey,
+    pub vesting_recipient: Pubkey,
+    pub vesting_end: i64,
+    pub epoch_shares: u128,
+    pub epoch_checkpoint: u64,
+    pub next_claim_epoch: u64,
+    pub position_mint: Pubkey,
+    pub bump: u8,
+}
+
 //************************* HELPER FUNCTIONS *************************//
 
 fn program_keypair_path() -> PathBuf {
@@ -59,6 +161,313 @@ fn get_stake_vault_pda(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
     )
 }
 
+// Helper function to derive the bonus distribution and bonus vault PDAs
+fn get_bonus_pdas(pool: &Pubkey, program_id: &Pubkey) -> (Pubkey, Pubkey) {
+    let (bonus_distribution, _) = Pubkey::find_program_address(
+       &[BONUS_DISTRIBUTION_SEED.as_bytes(), pool.as_ref()],
+        program_id,
+    );
+    let (bonus_vault, _) = Pubkey::find_program_address(
+       &[BONUS_VAULT_SEED.as_bytes(), pool.as_ref()],
+        program_id,
+    );
+    (bonus_distribution, bonus_vault)
+}
+
+// Helper function to derive a user stake PDA
+fn get_user_stake_pda(pool: &Pubkey, owner: &Pubkey, position_index: u64, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+       &[USER_STAKE_SEED.as_bytes(), pool.as_ref(), owner.as_ref(), &position_index.to_le_bytes()],
+        program_id,
+    ).0
+}
+
+// Helper function to derive a position counter PDA
+fn get_position_counter_pda(pool: &Pubkey, owner: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+       &[POSITION_COUNTER_SEED.as_bytes(), pool.as_ref(), owner.as_ref()],
+        program_id,
+    ).0
+}
+
+// Helper function to derive the fee vault PDA
+fn get_fee_vault_pda(pool: &Pubkey, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+       &[FEE_VAULT_SEED.as_bytes(), pool.as_ref()],
+        program_id,
+    ).0
+}
+
+// Helper function to derive the vesting schedule PDA of a position's recipient and the pool's vesting vault PDA
+fn get_reward_vesting_pdas(pool: &Pubkey, user_stake: &Pubkey, recipient: &Pubkey, program_id: &Pubkey) -> (Pubkey, Pubkey) {
+    let (reward_vesting, _) = Pubkey::find_program_address(
+       &[REWARD_VESTING_SEED.as_bytes(), pool.as_ref(), user_stake.as_ref(), recipient.as_ref()],
+        program_id,
+    );
+    let (vesting_vault, _) = Pubkey::find_program_address(
+       &[REWARD_VESTING_VAULT_SEED.as_bytes(), pool.as_ref()],
+        program_id,
+    );
+    (reward_vesting, vesting_vault)
+}
+
+// Helper function to derive the record PDA of a finished epoch
+fn get_epoch_record_pda(pool: &Pubkey, epoch: u64, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+       &[EPOCH_RECORD_SEED.as_bytes(), pool.as_ref(), &epoch.to_le_bytes()],
+        program_id,
+    ).0
+}
+
+// Helper function to derive the term product, term vault and term reward vault PDAs
+fn get_term_pdas(pool: &Pubkey, term_id: u16, program_id: &Pubkey) -> (Pubkey, Pubkey, Pubkey) {
+    let (term_product, _) = Pubkey::find_program_address(
+       &[TERM_PRODUCT_SEED.as_bytes(), pool.as_ref(), &term_id.to_le_bytes()],
+        program_id,
+    );
+    let (term_vault, _) = Pubkey::find_program_address(
+       &[TERM_VAULT_SEED.as_bytes(), term_product.as_ref()],
+        program_id,
+    );
+    let (term_reward_vault, _) = Pubkey::find_program_address(
+       &[TERM_REWARD_VAULT_SEED.as_bytes(), term_product.as_ref()],
+        program_id,
+    );
+    (term_product, term_vault, term_reward_vault)
+}
+
+// Helper function to derive a term deposit PDA
+fn get_term_position_pda(term_product: &Pubkey, position_id: u64, program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+       &[TERM_POSITION_SEED.as_bytes(), term_product.as_ref(), &position_id.to_le_bytes()],
+        program_id,
+    ).0
+}
+
+// Helper function to derive an associated token account
+fn get_ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+    Pubkey::find_program_address(
+       &[owner.as_ref(), spl_token::ID.as_ref(), mint.as_ref()],
+        &ata_program,
+    ).0
+}
+
+// Helper function to derive the mint PDA of a position NFT and the Token-2022 account of `holder` for it
+fn get_position_nft_pdas(user_stake: &Pubkey, holder: &Pubkey, program_id: &Pubkey) -> (Pubkey, Pubkey) {
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+    let token_2022_program: Pubkey = TOKEN_2022_PROGRAM_ID.parse().unwrap();
+
+    let (position_mint, _) = Pubkey::find_program_address(
+       &[POSITION_MINT_SEED.as_bytes(), user_stake.as_ref()],
+        program_id,
+    );
+    let (position_nft, _) = Pubkey::find_program_address(
+       &[holder.as_ref(), token_2022_program.as_ref(), position_mint.as_ref()],
+        &ata_program,
+    );
+    (position_mint, position_nft)
+}
+
+// Helper function to read the balance of a token account
+fn token_balance(svm: &LiteSVM, token_account: &Pubkey) -> u64 {
+    let account = svm.get_account(token_account).expect("Token account should exist");
+    u64::from_le_bytes(account.data[64..72].try_into().unwrap())
+}
+
+// Helper function to read the pool state
+fn read_pool(svm: &LiteSVM, pool: &Pubkey) -> Pool {
+    let account = svm.get_account(pool).expect("Pool account should exist");
+    Pool::deserialize(&mut &account.data[8..]).expect("Failed to deserialize Pool")
+}
+
+// Helper function to read a user stake
+fn read_user_stake(svm: &LiteSVM, user_stake: &Pubkey) -> UserStake {
+    let account = svm.get_account(user_stake).expect("User stake account should exist");
+    UserStake::deserialize(&mut &account.data[8..]).expect("Failed to deserialize UserStake")
+}
+
+// Helper function to build an initialize_pool instruction over existing mints
+fn initialize_pool_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    stake_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    reward_rate: u64,
+    start_time: i64,
+) -> Instruction {
+    let (pool_pda, _bump) = get_pool_pda(stake_mint, program_id);
+    let (stake_vault_pda, _bump) = get_stake_vault_pda(&pool_pda, program_id);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new_readonly(*stake_mint, false),
+            AccountMeta::new_readonly(*reward_mint, false),
+            AccountMeta::new(stake_vault_pda, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("initialize_pool", &[&reward_rate.to_le_bytes(), &start_time.to_le_bytes()]),
+    }
+}
+
+// Helper function to initialize a pool over existing mints that starts right away, returns the pool
+fn initialize_pool(
+    svm: &mut LiteSVM,
+    program_id: &Pubkey,
+    admin: &Keypair,
+    stake_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    reward_rate: u64,
+) -> Pubkey {
+    let instruction = initialize_pool_instruction(program_id, &admin.pubkey(), stake_mint, reward_mint, reward_rate, 0);
+    send(svm, instruction, &[admin]).expect("Initialize pool should succeed");
+
+    get_pool_pda(stake_mint, program_id).0
+}
+
+// Helper function to create a pool whose reward mint is owned by the pool, returns (pool, reward_mint)
+// The admin keeps the stake mint authority to fund stakers
+fn create_pool(svm: &mut LiteSVM, program_id: &Pubkey, admin: &Keypair, reward_rate: u64) -> (Pubkey, Pubkey) {
+    let mint = create_token_mint(svm, admin);
+    let (pool_pda, _bump) = get_pool_pda(&mint, program_id);
+
+    let reward_mint = CreateMint::new(svm, admin)
+    .authority(&pool_pda)
+    .decimals(9)
+    .send()
+    .unwrap();
+
+    initialize_pool(svm, program_id, admin, &mint, &reward_mint, reward_rate);
+
+    (pool_pda, reward_mint)
+}
+
+// Helper function to create a pool paying rewards in its stake token, returns (pool, mint)
+// The stakers are funded before the mint authority moves to the pool
+fn create_compound_pool(
+    svm: &mut LiteSVM,
+    program_id: &Pubkey,
+    admin: &Keypair,
+    reward_rate: u64,
+    stakers: &[&Keypair],
+    amount: u64,
+) -> (Pubkey, Pubkey) {
+    let mint = create_token_mint(svm, admin);
+    let (pool_pda, _bump) = get_pool_pda(&mint, program_id);
+
+    for staker in stakers {
+        fund_user(svm, admin, staker, &mint, amount);
+    }
+
+    SetAuthority::new(svm, admin, &mint, AuthorityType::MintTokens)
+    .new_authority(&pool_pda)
+    .send()
+    .unwrap();
+
+    initialize_pool(svm, program_id, admin, &mint, &mint, reward_rate);
+
+    (pool_pda, mint)
+}
+
+// Helper function to give a user lamports and stake tokens, returns the user's stake token account
+fn fund_user(svm: &mut LiteSVM, mint_authority: &Keypair, user: &Keypair, stake_mint: &Pubkey, amount: u64) -> Pubkey {
+    svm.airdrop(&user.pubkey(), 1_000_000_000).unwrap();
+
+    let user_ata = CreateAssociatedTokenAccount::new(svm, user, stake_mint).send().unwrap();
+    MintTo::new(svm, mint_authority, stake_mint, &user_ata, amount).send().unwrap();
+
+    user_ata
+}
+
+// Helper function to build a stake instruction into a position without a referrer, fee vault or NFT
+// The optional accounts are passed as the program id, callers replace them when needed
+fn stake_instruction(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    stake_mint: &Pubkey,
+    user: &Pubkey,
+    amount: u64,
+    lock_weeks: u16,
+    position_index: u64,
+) -> Instruction {
+    let (stake_vault, _bump) = get_stake_vault_pda(pool, program_id);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(get_ata(user, stake_mint), false),
+            AccountMeta::new_readonly(*stake_mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(get_user_stake_pda(pool, user, position_index, program_id), false),
+            AccountMeta::new(get_position_counter_pda(pool, user, program_id), false),
+            AccountMeta::new_readonly(*program_id, false), // fee_vault
+            AccountMeta::new_readonly(*program_id, false), // referrer_counter
+            AccountMeta::new_readonly(*program_id, false), // position_mint
+            AccountMeta::new_readonly(*program_id, false), // position_nft
+            AccountMeta::new_readonly(*program_id, false), // position_token_program
+            AccountMeta::new_readonly(*program_id, false), // associated_token_program
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data(
+            "stake",
+            &[
+                &amount.to_le_bytes(),
+                &lock_weeks.to_le_bytes(),
+                &[0], // no referrer
+                &position_index.to_le_bytes(),
+                &0u128.to_le_bytes(),
+                &[0], // no deadline
+            ],
+        ),
+    }
+}
+
+// Helper function to build a claim_bonus instruction
+fn claim_bonus_instruction(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    reward_mint: &Pubkey,
+    claimant: &Pubkey,
+    index: u32,
+    amount: u64,
+    proof: &[[u8; 32]],
+) -> Instruction {
+    let (bonus_distribution, bonus_vault) = get_bonus_pdas(pool, program_id);
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+
+    let mut instruction_data = get_discriminator("claim_bonus").to_vec();
+    instruction_data.extend_from_slice(&index.to_le_bytes());
+    instruction_data.extend_from_slice(&amount.to_le_bytes());
+    instruction_data.extend_from_slice(&(proof.len() as u32).to_le_bytes());
+    for node in proof {
+        instruction_data.extend_from_slice(node);
+    }
+    instruction_data.push(0); // no deadline
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*claimant, true),
+            AccountMeta::new_readonly(*pool, false),
+            AccountMeta::new_readonly(*reward_mint, false),
+            AccountMeta::new(bonus_distribution, false),
+            AccountMeta::new(bonus_vault, false),
+            AccountMeta::new(get_ata(claimant, reward_mint), false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ata_program, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data,
+    }
+}
+
 // Helper function to calculate instruction discriminator
 fn get_discriminator(instruction_name: &str) -> [u8; 8] {
     let mut hasher = Sha256::new();
@@ -69,6 +478,146 @@ fn get_discriminator(instruction_name: &str) -> [u8; 8] {
     discriminator
 }
 
+// Helper function to build an unstake, unstake_amount or unstake_all instruction without the optional accounts
+// The optional accounts are passed as the program id, callers replace them when needed
+// The position counter is the signer's, callers unstaking a position owned by another wallet replace it
+fn unstake_instruction(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    stake_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    user: &Pubkey,
+    user_stake: &Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    let (stake_vault, _bump) = get_stake_vault_pda(pool, program_id);
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(*user_stake, false),
+            AccountMeta::new(get_position_counter_pda(pool, user, program_id), false),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*stake_mint, false),
+            AccountMeta::new(*reward_mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(get_ata(user, stake_mint), false),
+            AccountMeta::new(get_ata(user, reward_mint), false),
+            AccountMeta::new_readonly(*program_id, false), // reward_vesting
+            AccountMeta::new_readonly(*program_id, false), // vesting_vault
+            AccountMeta::new_readonly(*program_id, false), // referrer_account
+            AccountMeta::new_readonly(*program_id, false), // treasury_reward_ata
+            AccountMeta::new_readonly(*program_id, false), // fee_vault
+            AccountMeta::new_readonly(*program_id, false), // position_nft
+            AccountMeta::new_readonly(*program_id, false), // position_mint
+            AccountMeta::new_readonly(*program_id, false), // position_token_program
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data,
+    }
+}
+
+// Helper function to build a claim_reward instruction paying the user, without the optional accounts
+fn claim_reward_instruction(
+    program_id: &Pubkey,
+    pool: &Pubkey,
+    stake_mint: &Pubkey,
+    reward_mint: &Pubkey,
+    user: &Pubkey,
+    user_stake: &Pubkey,
+) -> Instruction {
+    let (stake_vault, _bump) = get_stake_vault_pda(pool, program_id);
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new(*user_stake, false),
+            AccountMeta::new_readonly(*program_id, false), // position_nft
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new_readonly(*stake_mint, false),
+            AccountMeta::new(*reward_mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(get_ata(user, reward_mint), false),
+            AccountMeta::new_readonly(*program_id, false), // reward_vesting
+            AccountMeta::new_readonly(*program_id, false), // vesting_vault
+            AccountMeta::new_readonly(*program_id, false), // referrer_account
+            AccountMeta::new_readonly(*program_id, false), // treasury_reward_ata
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ata_program, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("claim_reward", &[&[0]]),
+    }
+}
+
+// Helper function to build a set_principal_fees instruction, creating the fee vault on first use
+fn set_principal_fees_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    pool: &Pubkey,
+    stake_mint: &Pubkey,
+    deposit_fee_bps: u16,
+    withdraw_fee_bps: u16,
+) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(*pool, false),
+            AccountMeta::new_readonly(*stake_mint, false),
+            AccountMeta::new(get_fee_vault_pda(pool, program_id), false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("set_principal_fees", &[&deposit_fee_bps.to_le_bytes(), &withdraw_fee_bps.to_le_bytes()]),
+    }
+}
+
+// Helper function to build an admin instruction taking the admin and the pool
+fn admin_instruction(program_id: &Pubkey, admin: &Pubkey, pool: &Pubkey, instruction_name: &str, args: &[&[u8]]) -> Instruction {
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(*pool, false),
+        ],
+        data: instruction_data(instruction_name, args),
+    }
+}
+
+// Helper function to build instruction data from the discriminator and the borsh encoded arguments
+fn instruction_data(instruction_name: &str, args: &[&[u8]]) -> Vec<u8> {
+    let mut data = get_discriminator(instruction_name).to_vec();
+    for arg in args {
+        data.extend_from_slice(arg);
+    }
+    data
+}
+
+// Helper function to sign and send a single instruction, the first signer pays
+fn send(
+    svm: &mut LiteSVM,
+    instruction: Instruction,
+    signers: &[&Keypair],
+) -> Result<TransactionMetadata, Box<FailedTransactionMetadata>> {
+    // A fresh blockhash keeps repeated identical transactions apart
+    svm.expire_blockhash();
+    let tx = Transaction::new_signed_with_payer(&[instruction], Some(&signers[0].pubkey()), signers, svm.latest_blockhash());
+    svm.send_transaction(tx).map_err(Box::new)
+}
+
+// Helper function to move the clock to a unix time
+fn warp_to(svm: &mut LiteSVM, unix_timestamp: i64) {
+    let mut clock = svm.get_sysvar::<Clock>();
+    clock.unix_timestamp = unix_timestamp;
+    svm.set_sysvar(&clock);
+}
+
 // Helper to create a token mint
 fn create_token_mint(svm: &mut LiteSVM, payer: &Keypair) -> Pubkey {
     let mint = CreateMint::new(svm, &payer)
@@ -142,6 +691,7 @@ fn initialize_staking() {
     instruction_data.extend_from_slice(&discriminator);
     let reward_rate_bytes = REWARD_RATE.to_ne_bytes();
     instruction_data.extend_from_slice(&reward_rate_bytes);
+    instruction_data.extend_from_slice(&0i64.to_le_bytes());
 
     // Build the instruction to initialize staking pool
     let instruction = Instruction {
@@ -185,11 +735,1891 @@ fn initialize_staking() {
     assert_eq!(pool.stake_mint, mint);
     assert_eq!(pool.reward_mint, reward_mint);
     assert_eq!(pool.stake_vault, stake_vault_pda);
+    assert_eq!(pool.stake_decimals, 9);
+    assert_eq!(pool.reward_decimals, 9);
     assert_eq!(pool.reward_rate, REWARD_RATE);
     assert_eq!(pool.total_stake, 0);
     assert_eq!(pool.total_shares, 0);
+    assert_eq!(pool.total_weighted_shares, 0);
     assert_eq!(pool.acc_reward_per_share, 0);
+    assert_eq!(pool.reward_remainder, 0);
     assert_eq!(pool.last_update_time, 0);
+    assert_eq!(pool.start_time, 0);
+    assert!(!pool.allow_pre_deposit);
+    assert_eq!(pool.deposit_open, 0);
+    assert_eq!(pool.deposit_close, 0);
+    assert_eq!(pool.max_total_stake, 0);
+    assert_eq!(pool.max_user_stake, 0);
+    assert_eq!(pool.min_stake_amount, 0);
+    assert_eq!(pool.emission_segment_count, 0);
+    assert_eq!(pool.halving_interval, 0);
+    assert_eq!(pool.halving_start, 0);
+    assert_eq!(pool.max_total_rewards, 0);
+    assert_eq!(pool.total_rewards_accrued, 0);
+    assert_eq!(pool.total_rewards_paid, 0);
+    assert_eq!(pool.reward_rate_mode, 0);
+    assert_eq!(pool.reward_apr_bps, 0);
+    assert_eq!(pool.price_ratio_num, 1);
+    assert_eq!(pool.price_ratio_den, 1);
+    assert_eq!(pool.rate_curve.target_stake, 0);
+    assert_eq!(pool.epoch_duration, 0);
+    assert_eq!(pool.current_epoch, 0);
+    assert_eq!(pool.epoch_start, 0);
+    assert_eq!(pool.epoch_eligible_shares, 0);
+    assert_eq!(pool.keeper_fee_bps, 0);
+    assert_eq!(pool.vesting_duration, 0);
+    assert_eq!(pool.vesting_cliff, 0);
+    assert!(!pool.forfeit_unvested_on_exit);
+    assert_eq!(pool.max_lock_weeks, 0);
+    assert_eq!(pool.max_boost_bps, 10_000);
+    assert_eq!(pool.loyalty_max_bps, 10_000);
+    assert_eq!(pool.loyalty_period, 0);
+    assert_eq!(pool.loyalty_decay_bps, 10_000);
+    assert_eq!(pool.referral_bps, 0);
+    assert_eq!(pool.fee_bps, 0);
+    assert_eq!(pool.fee_recipient, admin.pubkey());
+    assert_eq!(pool.deposit_fee_bps, 0);
+    assert_eq!(pool.withdraw_fee_bps, 0);
     assert_eq!(pool.paused, false);
     assert_eq!(pool.bump, bump);
 }
+
+#[test]
+fn claim_bonus_with_merkle_proof() {
+    let (program_id, mut svm) = deploy_staking_program();
+
+    let admin = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 115_740);
+    let (bonus_distribution, bonus_vault) = get_bonus_pdas(&pool_pda, &program_id);
+
+    // Build the campaign off-chain
+    let claimants: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+    let amounts = [1_000u64, 2_000u64, 3_000u64];
+    let leaves = claimants
+        .iter()
+        .zip(amounts)
+        .enumerate()
+        .map(|(index, (claimant, amount))| leaf_hash(index as u32, claimant.pubkey().as_ref(), amount))
+        .collect();
+    let tree = MerkleTree::new(leaves);
+
+    // Fund the campaign
+    let mut instruction_data = get_discriminator("set_bonus_distribution").to_vec();
+    instruction_data.extend_from_slice(&tree.root());
+    instruction_data.extend_from_slice(&amounts.iter().sum::<u64>().to_le_bytes());
+    instruction_data.extend_from_slice(&1_000i64.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(admin.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(reward_mint, false),
+            AccountMeta::new(bonus_distribution, false),
+            AccountMeta::new(bonus_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data,
+    };
+
+    let tx = Transaction::new_signed_with_payer(&[instruction], Some(&admin.pubkey()), &[&admin], svm.latest_blockhash());
+    svm.send_transaction(tx).expect("Set bonus distribution should succeed");
+    assert_eq!(token_balance(&svm, &bonus_vault), 6_000);
+
+    // Claim the second leaf
+    let claimant = &claimants[1];
+    svm.airdrop(&claimant.pubkey(), 1_000_000_000).unwrap();
+
+    let instruction = claim_bonus_instruction(&program_id, &pool_pda, &reward_mint, &claimant.pubkey(), 1, 2_000, &tree.proof(1));
+    let tx = Transaction::new_signed_with_payer(&[instruction], Some(&claimant.pubkey()), &[claimant], svm.latest_blockhash());
+    svm.send_transaction(tx).expect("Claim bonus should succeed");
+
+    assert_eq!(token_balance(&svm, &get_ata(&claimant.pubkey(), &reward_mint)), 2_000);
+    assert_eq!(token_balance(&svm, &bonus_vault), 4_000);
+
+    // A leaf can only be claimed once
+    svm.expire_blockhash();
+    let instruction = claim_bonus_instruction(&program_id, &pool_pda, &reward_mint, &claimant.pubkey(), 1, 2_000, &tree.proof(1));
+    let tx = Transaction::new_signed_with_payer(&[instruction], Some(&claimant.pubkey()), &[claimant], svm.latest_blockhash());
+    assert!(svm.send_transaction(tx).is_err(), "Double claim should fail");
+
+    // A claim executed after its deadline fails
+    let claimant = &claimants[0];
+    svm.airdrop(&claimant.pubkey(), 1_000_000_000).unwrap();
+
+    let mut instruction = claim_bonus_instruction(&program_id, &pool_pda, &reward_mint, &claimant.pubkey(), 0, 1_000, &tree.proof(0));
+    instruction.data.pop();
+    instruction.data.push(1);
+    instruction.data.extend_from_slice(&(-1i64).to_le_bytes());
+    let tx = Transaction::new_signed_with_payer(&[instruction], Some(&claimant.pubkey()), &[claimant], svm.latest_blockhash());
+    assert!(svm.send_transaction(tx).is_err(), "Expired claim should fail");
+
+    // Another claimant can not use a proof with an inflated amount
+    let claimant = &claimants[2];
+    svm.airdrop(&claimant.pubkey(), 1_000_000_000).unwrap();
+
+    let instruction = claim_bonus_instruction(&program_id, &pool_pda, &reward_mint, &claimant.pubkey(), 2, 4_000, &tree.proof(2));
+    let tx = Transaction::new_signed_with_payer(&[instruction], Some(&claimant.pubkey()), &[claimant], svm.latest_blockhash());
+    assert!(svm.send_transaction(tx).is_err(), "Claim with a wrong amount should fail");
+}
+
+#[test]
+fn compound_rewards_into_shares() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, mint) = create_compound_pool(&mut svm, &program_id, &admin, 1_000, &[&user], 1_000_000);
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    // 100 seconds at 1_000 per second are reinvested into the position
+    warp_to(&mut svm, START_TIME + 100);
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+            AccountMeta::new(mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new_readonly(program_id, false), // referrer_account
+            AccountMeta::new_readonly(program_id, false), // treasury_reward_ata
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("compound", &[&[0]]),
+    };
+    send(&mut svm, instruction, &[&user]).expect("Compound should succeed");
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(token_balance(&svm, &stake_vault), 1_100_000);
+    assert_eq!(pool.total_stake, 1_100_000);
+    assert_eq!(read_user_stake(&svm, &user_stake).shares, 1_100_000);
+    assert_eq!(token_balance(&svm, &get_ata(&user.pubkey(), &mint)), 0);
+
+    // Rewards paid in another token can not be compounded
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, _reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+    warp_to(&mut svm, START_TIME + 100);
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+            AccountMeta::new(mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(program_id, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("compound", &[&[0]]),
+    };
+    assert!(send(&mut svm, instruction, &[&user]).is_err(), "Compound with another reward mint should fail");
+}
+
+#[test]
+fn crank_compound_within_keeper_fee_limits() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let keeper = Keypair::new();
+    let users = [Keypair::new(), Keypair::new()];
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+    svm.airdrop(&keeper.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, mint) = create_compound_pool(&mut svm, &program_id, &admin, 1_000, &[&users[0], &users[1]], 1_000_000);
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let user_stakes = users.each_ref().map(|user| get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id));
+
+    // The keeper fee is capped
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_keeper_fee", &[&501u16.to_le_bytes()]);
+    assert!(send(&mut svm, instruction, &[&admin]).is_err(), "Keeper fee above the cap should fail");
+
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_keeper_fee", &[&100u16.to_le_bytes()]);
+    send(&mut svm, instruction, &[&admin]).expect("Set keeper fee should succeed");
+
+    // The first user accepts the 1% fee, the second only 0.5%
+    for ((user, user_stake), max_keeper_fee_bps) in users.iter().zip(user_stakes).zip([100u16, 50u16]) {
+        let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+        send(&mut svm, instruction, &[user]).expect("Stake should succeed");
+
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(user.pubkey(), true),
+                AccountMeta::new_readonly(pool_pda, false),
+                AccountMeta::new(user_stake, false),
+            ],
+            data: instruction_data("set_auto_compound", &[&[1], &max_keeper_fee_bps.to_le_bytes(), &[0]]),
+        };
+        send(&mut svm, instruction, &[user]).expect("Set auto compound should succeed");
+    }
+
+    // Each position earns 50_000 over 100 seconds
+    warp_to(&mut svm, START_TIME + 100);
+
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+    let keeper_ata = get_ata(&keeper.pubkey(), &mint);
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(keeper.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(keeper_ata, false),
+            AccountMeta::new_readonly(program_id, false), // treasury_reward_ata
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ata_program, false),
+            AccountMeta::new_readonly(ID, false),
+            AccountMeta::new(user_stakes[0], false),
+            AccountMeta::new(user_stakes[1], false),
+        ],
+        data: instruction_data("crank_compound", &[]),
+    };
+    send(&mut svm, instruction, &[&keeper]).expect("Crank compound should succeed");
+
+    // Only the first position is compounded, net of the keeper's 1%
+    assert_eq!(token_balance(&svm, &keeper_ata), 500);
+    assert_eq!(token_balance(&svm, &stake_vault), 2_049_500);
+    assert_eq!(read_user_stake(&svm, &user_stakes[0]).shares, 1_049_500);
+    assert_eq!(read_user_stake(&svm, &user_stakes[1]).shares, 1_000_000);
+    assert_eq!(read_pool(&svm, &pool_pda).total_stake, 2_049_500);
+}
+
+#[test]
+fn vested_rewards_release_linearly_and_forfeit_on_exit() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let other = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 100);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let other_stake = get_user_stake_pda(&pool_pda, &other.pubkey(), 0, &program_id);
+    let (reward_vesting, vesting_vault) = get_reward_vesting_pdas(&pool_pda, &user_stake, &user.pubkey(), &program_id);
+    let (other_vesting, _) = get_reward_vesting_pdas(&pool_pda, &other_stake, &other.pubkey(), &program_id);
+    let user_reward_ata = get_ata(&user.pubkey(), &reward_mint);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    fund_user(&mut svm, &admin, &other, &mint, 1_000_000);
+
+    // Claims vest over 1_000 seconds after a 200 second cliff, and are forfeited on a full exit
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(admin.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new_readonly(reward_mint, false),
+            AccountMeta::new(vesting_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("set_vesting", &[&1_000i64.to_le_bytes(), &200i64.to_le_bytes(), &[1]]),
+    };
+    send(&mut svm, instruction, &[&admin]).expect("Set vesting should succeed");
+
+    // Both positions earn 50 per second
+    for staker in [&user, &other] {
+        let instruction = stake_instruction(&program_id, &pool_pda, &mint, &staker.pubkey(), 1_000_000, 0, 0);
+        send(&mut svm, instruction, &[staker]).expect("Stake should succeed");
+    }
+
+    let claim = |signer: &Pubkey, position: &Pubkey, schedule: &Pubkey| {
+        let mut instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, signer, position);
+        instruction.accounts[9] = AccountMeta::new(*schedule, false);
+        instruction.accounts[10] = AccountMeta::new(vesting_vault, false);
+        instruction
+    };
+    let withdraw_vested = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new_readonly(user_stake, false),
+            AccountMeta::new(reward_vesting, false),
+            AccountMeta::new_readonly(reward_mint, false),
+            AccountMeta::new(vesting_vault, false),
+            AccountMeta::new(user_reward_ata, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap(), false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("withdraw_vested", &[&[0]]),
+    };
+
+    // The claim goes into the vesting vault, vesting until START_TIME + 2_000
+    warp_to(&mut svm, START_TIME + 1_000);
+    send(&mut svm, claim(&user.pubkey(), &user_stake, &reward_vesting), &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &vesting_vault), 50_000);
+    assert_eq!(token_balance(&svm, &user_reward_ata), 0);
+
+    // Nothing is released before the cliff
+    warp_to(&mut svm, START_TIME + 1_100);
+    send(&mut svm, withdraw_vested.clone(), &[&user]).expect("Withdraw vested should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 0);
+
+    // Halfway through, half of it is released
+    warp_to(&mut svm, START_TIME + 1_500);
+    send(&mut svm, withdraw_vested.clone(), &[&user]).expect("Withdraw vested should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 25_000);
+
+    // The 25_000 still locked merge with a new 25_000 tranche. The merged schedule ends at the average
+    // of 500 and 1_000 seconds from now, and its cliff at the average of 0 and 200 seconds
+    send(&mut svm, claim(&user.pubkey(), &user_stake, &reward_vesting), &[&user]).expect("Claim should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).vesting_end, START_TIME + 2_250);
+
+    warp_to(&mut svm, START_TIME + 1_550);
+    send(&mut svm, withdraw_vested.clone(), &[&user]).expect("Withdraw vested should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 25_000);
+
+    // 375 of the 750 seconds release half of the merged 50_000, which stays in the vault for now
+    warp_to(&mut svm, START_TIME + 1_875);
+    send(&mut svm, claim(&user.pubkey(), &user_stake, &reward_vesting), &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &vesting_vault), 68_750);
+
+    // Nothing is pending after the claim, yet the exit can not skip the schedule
+    let data = instruction_data("unstake_all", &[&0u64.to_le_bytes(), &[0], &[0]]);
+    let mut unstake_all = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+    assert!(send(&mut svm, unstake_all.clone(), &[&user]).is_err(), "Exit without the vesting accounts should fail");
+
+    // Nor move it to another recipient first
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(user.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+        ],
+        data: instruction_data("set_reward_recipient", &[other.pubkey().as_ref(), &[0]]),
+    };
+    assert!(send(&mut svm, instruction, &[&user]).is_err(), "Redirecting vesting rewards should fail");
+
+    // The exit forfeits the 43_750 still locked to the remaining staker, the 25_000 released stay withdrawable
+    unstake_all.accounts[9] = AccountMeta::new(reward_vesting, false);
+    unstake_all.accounts[10] = AccountMeta::new(vesting_vault, false);
+    send(&mut svm, unstake_all, &[&user]).expect("Unstake all should succeed");
+    assert_eq!(token_balance(&svm, &vesting_vault), 25_000);
+
+    send(&mut svm, withdraw_vested, &[&user]).expect("Withdraw vested should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 50_000);
+    assert_eq!(token_balance(&svm, &vesting_vault), 0);
+
+    // The other staker earned 1_875 seconds at 50 per second, plus the forfeit
+    send(&mut svm, claim(&other.pubkey(), &other_stake, &other_vesting), &[&other]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &vesting_vault), 93_750 + 43_750);
+}
+
+#[test]
+fn lock_boost_weights_rewards() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let locker = Keypair::new();
+    let staker = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 3_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let locker_stake = get_user_stake_pda(&pool_pda, &locker.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &locker, &mint, 1_000_000);
+    fund_user(&mut svm, &admin, &staker, &mint, 1_000_000);
+
+    // A 52 week lock doubles the reward weight
+    let args: [&[u8]; 2] = [&52u16.to_le_bytes(), &20_000u16.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_lock_config", &args);
+    send(&mut svm, instruction, &[&admin]).expect("Set lock config should succeed");
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &locker.pubkey(), 1_000_000, 52, 0);
+    send(&mut svm, instruction, &[&locker]).expect("Locked stake should succeed");
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &staker.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&staker]).expect("Unlocked stake should succeed");
+
+    let position = read_user_stake(&svm, &locker_stake);
+    assert_eq!(position.boost_bps, 20_000);
+    assert_eq!(position.weighted_shares, 2_000_000);
+    assert_eq!(position.lock_end, START_TIME + 52 * 604_800);
+    assert_eq!(read_pool(&svm, &pool_pda).total_weighted_shares, 3_000_000);
+
+    // The locker earns two thirds of the 300_000 emitted over 100 seconds
+    warp_to(&mut svm, START_TIME + 100);
+
+    let instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &locker.pubkey(), &locker_stake);
+    send(&mut svm, instruction, &[&locker]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&locker.pubkey(), &reward_mint)), 200_000);
+
+    // The shares can not leave before the lock ends
+    let data = instruction_data("unstake", &[&1_000_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &locker.pubkey(), &locker_stake, data.clone());
+    assert!(send(&mut svm, instruction, &[&locker]).is_err(), "Unstake of a locked position should fail");
+
+    warp_to(&mut svm, position.lock_end);
+
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &locker.pubkey(), &locker_stake, data);
+    send(&mut svm, instruction, &[&locker]).expect("Unstake after the lock should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&locker.pubkey(), &mint)), 1_000_000);
+    assert_eq!(read_user_stake(&svm, &locker_stake).shares, 0);
+}
+
+#[test]
+fn loyalty_grows_with_staking_time() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    CreateAssociatedTokenAccount::new(&mut svm, &user, &reward_mint).send().unwrap();
+
+    // Up to 1.5x after 1_000 seconds, fully reset by an unstake
+    let args: [&[u8]; 3] = [&15_000u16.to_le_bytes(), &1_000i64.to_le_bytes(), &10_000u16.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_loyalty_config", &args);
+    send(&mut svm, instruction, &[&admin]).expect("Set loyalty config should succeed");
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).weighted_shares, 1_000_000);
+
+    let kick = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+        ],
+        data: instruction_data("kick", &[]),
+    };
+
+    // Halfway through the period the multiplier is 1.25x
+    warp_to(&mut svm, START_TIME + 500);
+    send(&mut svm, kick.clone(), &[&admin]).expect("Kick should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).weighted_shares, 1_250_000);
+
+    // And it stops growing at the max
+    warp_to(&mut svm, START_TIME + 2_000);
+    send(&mut svm, kick.clone(), &[&admin]).expect("Kick should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).weighted_shares, 1_500_000);
+    assert_eq!(read_pool(&svm, &pool_pda).total_weighted_shares, 1_500_000);
+
+    // Unstaking starts the period over
+    let data = instruction_data("unstake", &[&500_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+    send(&mut svm, instruction, &[&user]).expect("Unstake should succeed");
+
+    let position = read_user_stake(&svm, &user_stake);
+    assert_eq!(position.last_stake_time, START_TIME + 2_000);
+    assert_eq!(position.weighted_shares, 500_000);
+}
+
+#[test]
+fn top_up_averages_the_loyalty_period() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, _reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+
+    // Up to 1.5x after 1_000 seconds
+    let args: [&[u8]; 3] = [&15_000u16.to_le_bytes(), &1_000i64.to_le_bytes(), &10_000u16.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_loyalty_config", &args);
+    send(&mut svm, instruction, &[&admin]).expect("Set loyalty config should succeed");
+
+    let kick = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+        ],
+        data: instruction_data("kick", &[]),
+    };
+
+    // A small position long past the loyalty period
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 100_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    warp_to(&mut svm, START_TIME + 5_000);
+    send(&mut svm, kick.clone(), &[&admin]).expect("Kick should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).weighted_shares, 150_000);
+
+    // The top-up does not inherit the full multiplier, the capped 1_000 seconds are averaged over ten times the shares
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 900_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Top-up should succeed");
+
+    let position = read_user_stake(&svm, &user_stake);
+    assert_eq!(position.last_stake_time, START_TIME + 4_900);
+    assert_eq!(position.weighted_shares, 1_050_000);
+    assert_eq!(read_pool(&svm, &pool_pda).total_weighted_shares, 1_050_000);
+
+    // The whole position matures a period after its averaged start
+    warp_to(&mut svm, START_TIME + 5_900);
+    send(&mut svm, kick, &[&admin]).expect("Kick should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).weighted_shares, 1_500_000);
+}
+
+#[test]
+fn referral_rewards_and_loop_rejection() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let [a, b, c] = [Keypair::new(), Keypair::new(), Keypair::new()];
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 3_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let [a_counter, b_counter, c_counter] = [&a, &b, &c].map(|user| get_position_counter_pda(&pool_pda, &user.pubkey(), &program_id));
+    let (referrer_account, _) = Pubkey::find_program_address(&[b"REFERRER", pool_pda.as_ref(), a.pubkey().as_ref()], &program_id);
+    for user in [&a, &b, &c] {
+        fund_user(&mut svm, &admin, user, &mint, 2_000_000);
+    }
+
+    // Referrers earn 10% of their referees' rewards
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_referral", &[&1_000u16.to_le_bytes()]);
+    send(&mut svm, instruction, &[&admin]).expect("Set referral should succeed");
+
+    // A stake recording `referrer`, followed by the position counters of the referrer's chain
+    let stake_referred = |user: &Pubkey, referrer: &Pubkey, chain: &[Pubkey]| {
+        let mut instruction = stake_instruction(&program_id, &pool_pda, &mint, user, 1_000_000, 0, 0);
+        instruction.data.splice(18..19, [&[1u8][..], referrer.as_ref()].concat());
+        instruction.accounts[8] = AccountMeta::new_readonly(chain[0], false);
+        instruction.accounts.extend(chain[1..].iter().map(|counter| AccountMeta::new_readonly(*counter, false)));
+        instruction
+    };
+
+    // A is referred by nobody, B by A and C by B
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &a.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&a]).expect("Stake should succeed");
+    send(&mut svm, stake_referred(&b.pubkey(), &a.pubkey(), &[a_counter]), &[&b]).expect("Referred stake should succeed");
+
+    // The whole chain up to A must be passed
+    let instruction = stake_referred(&c.pubkey(), &b.pubkey(), &[b_counter]);
+    assert!(send(&mut svm, instruction, &[&c]).is_err(), "Stake with a partial referral chain should fail");
+    send(&mut svm, stake_referred(&c.pubkey(), &b.pubkey(), &[b_counter, a_counter]), &[&c]).expect("Referred stake should succeed");
+
+    // A can not refer itself, nor close the loop A -> C -> B -> A
+    let instruction = stake_referred(&a.pubkey(), &a.pubkey(), &[a_counter]);
+    assert!(send(&mut svm, instruction, &[&a]).is_err(), "Self-referral should fail");
+    let instruction = stake_referred(&a.pubkey(), &c.pubkey(), &[c_counter, b_counter]);
+    assert!(send(&mut svm, instruction, &[&a]).is_err(), "Referral loop should fail");
+
+    let b_stake = get_user_stake_pda(&pool_pda, &b.pubkey(), 0, &program_id);
+    assert_eq!(read_user_stake(&svm, &b_stake).referrer, a.pubkey());
+    assert_eq!(read_user_stake(&svm, &get_user_stake_pda(&pool_pda, &a.pubkey(), 0, &program_id)).referrer, Pubkey::default());
+
+    // B earns 100_000 over 100 seconds, 10_000 of which go to A
+    warp_to(&mut svm, START_TIME + 100);
+
+    let mut instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &b.pubkey(), &b_stake);
+    assert!(send(&mut svm, instruction.clone(), &[&b]).is_err(), "Claim without the referrer account should fail");
+
+    instruction.accounts[11] = AccountMeta::new(referrer_account, false);
+    send(&mut svm, instruction, &[&b]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&b.pubkey(), &reward_mint)), 90_000);
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(a.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new(referrer_account, false),
+            AccountMeta::new(reward_mint, false),
+            AccountMeta::new(get_ata(&a.pubkey(), &reward_mint), false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap(), false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("claim_referral_reward", &[&[0]]),
+    };
+    send(&mut svm, instruction, &[&a]).expect("Claim referral reward should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&a.pubkey(), &reward_mint)), 10_000);
+}
+
+#[test]
+fn reward_recipient_and_claim_delegate() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let recipient = Keypair::new();
+    let delegate = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+    svm.airdrop(&delegate.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let user_ata = fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    let recipient_reward_ata = get_ata(&recipient.pubkey(), &reward_mint);
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    let set_position_key = |name: &str, key: &Pubkey| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(user.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+        ],
+        data: instruction_data(name, &[key.as_ref(), &[0]]),
+    };
+    send(&mut svm, set_position_key("set_reward_recipient", &recipient.pubkey()), &[&user])
+        .expect("Set reward recipient should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).reward_recipient, recipient.pubkey());
+
+    // Claims can only pay the recipient
+    warp_to(&mut svm, START_TIME + 100);
+    let mut claim = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake);
+    assert!(send(&mut svm, claim.clone(), &[&user]).is_err(), "Claim to the owner should fail");
+
+    claim.accounts[4] = AccountMeta::new_readonly(recipient.pubkey(), false);
+    claim.accounts[8] = AccountMeta::new(recipient_reward_ata, false);
+    send(&mut svm, claim, &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &recipient_reward_ata), 100_000);
+
+    // The delegate may not claim until it is set
+    let mut delegate_claim = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &delegate.pubkey(), &user_stake);
+    delegate_claim.accounts[4] = AccountMeta::new_readonly(recipient.pubkey(), false);
+    delegate_claim.accounts[8] = AccountMeta::new(recipient_reward_ata, false);
+    assert!(send(&mut svm, delegate_claim.clone(), &[&delegate]).is_err(), "Claim by a stranger should fail");
+
+    send(&mut svm, set_position_key("set_claim_delegate", &delegate.pubkey()), &[&user])
+        .expect("Set claim delegate should succeed");
+
+    // The delegate claims to the recipient, it never receives the rewards itself
+    warp_to(&mut svm, START_TIME + 200);
+    send(&mut svm, delegate_claim, &[&delegate]).expect("Delegate claim should succeed");
+    assert_eq!(token_balance(&svm, &recipient_reward_ata), 200_000);
+
+    // Nor can it withdraw the stake
+    let data = instruction_data("unstake", &[&1_000_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &delegate.pubkey(), &user_stake, data);
+    instruction.accounts[2] = AccountMeta::new(get_position_counter_pda(&pool_pda, &user.pubkey(), &program_id), false);
+    instruction.accounts[7] = AccountMeta::new(user_ata, false);
+    instruction.accounts[8] = AccountMeta::new(recipient_reward_ata, false);
+    assert!(send(&mut svm, instruction.clone(), &[&delegate]).is_err(), "Unstake by the delegate should fail");
+
+    // The owner withdraws, the rewards still go to the recipient
+    warp_to(&mut svm, START_TIME + 300);
+    instruction.accounts[0] = AccountMeta::new(user.pubkey(), true);
+    send(&mut svm, instruction, &[&user]).expect("Unstake should succeed");
+    assert_eq!(token_balance(&svm, &user_ata), 1_000_000);
+    assert_eq!(token_balance(&svm, &recipient_reward_ata), 300_000);
+}
+
+#[test]
+fn protocol_fee_splits_reward_payouts() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let treasury = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    // Rewards are paid in the stake token so claim, compound and unstake all go through the same accounts
+    let (pool_pda, mint) = create_compound_pool(&mut svm, &program_id, &admin, 1_000, &[&user], 1_000_000);
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let user_ata = get_ata(&user.pubkey(), &mint);
+    let treasury_ata = CreateAssociatedTokenAccount::new(&mut svm, &admin, &mint).owner(&treasury.pubkey()).send().unwrap();
+
+    let set_protocol_fee = |fee_bps: u16| {
+        admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_protocol_fee", &[&fee_bps.to_le_bytes(), treasury.pubkey().as_ref()])
+    };
+
+    // The fee is capped at 20%
+    assert!(send(&mut svm, set_protocol_fee(2_001), &[&admin]).is_err(), "Fee above the cap should fail");
+    send(&mut svm, set_protocol_fee(1_000), &[&admin]).expect("Set protocol fee should succeed");
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.fee_bps, 1_000);
+    assert_eq!(pool.fee_recipient, treasury.pubkey());
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    // Each payout of 100_000 sends 10% to the treasury
+    warp_to(&mut svm, START_TIME + 100);
+    let mut claim = claim_reward_instruction(&program_id, &pool_pda, &mint, &mint, &user.pubkey(), &user_stake);
+    assert!(send(&mut svm, claim.clone(), &[&user]).is_err(), "Claim without the treasury account should fail");
+
+    claim.accounts[12] = AccountMeta::new(treasury_ata, false);
+    send(&mut svm, claim, &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &user_ata), 90_000);
+    assert_eq!(token_balance(&svm, &treasury_ata), 10_000);
+
+    // Only the user's part is compounded
+    warp_to(&mut svm, START_TIME + 200);
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(user_stake, false),
+            AccountMeta::new(mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new_readonly(program_id, false), // referrer_account
+            AccountMeta::new(treasury_ata, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("compound", &[&[0]]),
+    };
+    send(&mut svm, instruction, &[&user]).expect("Compound should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).shares, 1_090_000);
+    assert_eq!(token_balance(&svm, &stake_vault), 1_090_000);
+    assert_eq!(token_balance(&svm, &treasury_ata), 20_000);
+
+    // The exit pays the stake and the rewards net of the fee
+    warp_to(&mut svm, START_TIME + 300);
+    let data = instruction_data("unstake", &[&1_090_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &mint, &user.pubkey(), &user_stake, data);
+    assert!(send(&mut svm, instruction.clone(), &[&user]).is_err(), "Unstake without the treasury account should fail");
+
+    instruction.accounts[12] = AccountMeta::new(treasury_ata, false);
+    send(&mut svm, instruction, &[&user]).expect("Unstake should succeed");
+    assert_eq!(token_balance(&svm, &user_ata), 90_000 + 1_090_000 + 90_000);
+    assert_eq!(token_balance(&svm, &treasury_ata), 30_000);
+    assert_eq!(token_balance(&svm, &stake_vault), 0);
+}
+
+#[test]
+fn principal_fees_go_to_the_fee_vault() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let fee_vault = get_fee_vault_pda(&pool_pda, &program_id);
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let user_ata = fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    CreateAssociatedTokenAccount::new(&mut svm, &user, &reward_mint).send().unwrap();
+
+    let set_principal_fees = |deposit_fee_bps: u16, withdraw_fee_bps: u16| {
+        set_principal_fees_instruction(&program_id, &admin.pubkey(), &pool_pda, &mint, deposit_fee_bps, withdraw_fee_bps)
+    };
+
+    // Fees are capped at 10%
+    assert!(send(&mut svm, set_principal_fees(1_001, 0), &[&admin]).is_err(), "Fee above the cap should fail");
+
+    // 1% on deposits, 2% on withdrawals
+    send(&mut svm, set_principal_fees(100, 200), &[&admin]).expect("Set principal fees should succeed");
+
+    // The fee vault must be passed once deposits are charged
+    let mut instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    assert!(send(&mut svm, instruction.clone(), &[&user]).is_err(), "Stake without the fee vault should fail");
+
+    instruction.accounts[7] = AccountMeta::new(fee_vault, false);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    assert_eq!(token_balance(&svm, &fee_vault), 10_000);
+    assert_eq!(token_balance(&svm, &stake_vault), 990_000);
+    assert_eq!(read_user_stake(&svm, &user_stake).shares, 990_000);
+
+    // Withdrawing everything pays 98% of the stake
+    let data = instruction_data("unstake", &[&990_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+    instruction.accounts[13] = AccountMeta::new(fee_vault, false);
+    send(&mut svm, instruction, &[&user]).expect("Unstake should succeed");
+
+    assert_eq!(token_balance(&svm, &user_ata), 970_200);
+    assert_eq!(token_balance(&svm, &fee_vault), 29_800);
+    assert_eq!(token_balance(&svm, &stake_vault), 0);
+}
+
+#[test]
+fn emission_halves_every_interval() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+
+    // Segments and halvings can not be combined
+    let segment = [START_TIME.to_le_bytes(), 1_000u64.to_le_bytes()].concat();
+    let args: [&[u8]; 3] = [&1u32.to_le_bytes(), &segment, &100i64.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_emission_schedule", &args);
+    assert!(send(&mut svm, instruction, &[&admin]).is_err(), "Segments with halvings should fail");
+
+    // Halve the rate every 100 seconds from now
+    let args: [&[u8]; 2] = [&0u32.to_le_bytes(), &100i64.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_emission_schedule", &args);
+    send(&mut svm, instruction, &[&admin]).expect("Set emission schedule should succeed");
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    // The third period runs at a quarter of the rate
+    warp_to(&mut svm, START_TIME + 250);
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new_readonly(pool_pda, false)],
+        data: instruction_data("current_emission_segment", &[]),
+    };
+    let meta = send(&mut svm, instruction, &[&user]).expect("Current emission segment should succeed");
+    let (start_time, rate) = <(i64, u64)>::deserialize(&mut meta.return_data.data.as_slice()).unwrap();
+    assert_eq!(start_time, START_TIME + 200);
+    assert_eq!(rate, 250);
+
+    // 100 * 1_000 + 100 * 500 + 50 * 250
+    let instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake);
+    send(&mut svm, instruction, &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&user.pubkey(), &reward_mint)), 162_500);
+}
+
+#[test]
+fn rewards_budget_caps_accrual_and_payouts() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let other = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let other_stake = get_user_stake_pda(&pool_pda, &other.pubkey(), 0, &program_id);
+    let user_reward_ata = get_ata(&user.pubkey(), &reward_mint);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    fund_user(&mut svm, &admin, &other, &mint, 1_000_000);
+    let other_reward_ata = CreateAssociatedTokenAccount::new(&mut svm, &other, &reward_mint).send().unwrap();
+
+    let set_reward_budget = |max_total_rewards: u128| {
+        admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_reward_budget", &[&max_total_rewards.to_le_bytes()])
+    };
+
+    // The pool will never emit more than 250_000
+    send(&mut svm, set_reward_budget(250_000), &[&admin]).expect("Set reward budget should succeed");
+
+    for staker in [&user, &other] {
+        let instruction = stake_instruction(&program_id, &pool_pda, &mint, &staker.pubkey(), 1_000_000, 0, 0);
+        send(&mut svm, instruction, &[staker]).expect("Stake should succeed");
+    }
+
+    let claim = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake);
+
+    // Each staker earns 500 per second while the budget lasts
+    warp_to(&mut svm, START_TIME + 100);
+    send(&mut svm, claim.clone(), &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 50_000);
+
+    // The budget can not be cut below what has already accrued
+    assert!(send(&mut svm, set_reward_budget(99_999), &[&admin]).is_err(), "Budget below the accrued rewards should fail");
+
+    // It runs out at 250 seconds, half way to the next claim
+    warp_to(&mut svm, START_TIME + 300);
+    send(&mut svm, claim.clone(), &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 125_000);
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.total_rewards_accrued, 250_000);
+    assert_eq!(pool.total_rewards_paid, 125_000);
+
+    // Nothing accrues past the budget, the other staker only gets its share of it
+    warp_to(&mut svm, START_TIME + 1_000);
+    let data = instruction_data("unstake", &[&1_000_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &other.pubkey(), &other_stake, data);
+    send(&mut svm, instruction, &[&other]).expect("Unstake should succeed");
+    assert_eq!(token_balance(&svm, &other_reward_ata), 125_000);
+
+    let data = instruction_data("unstake", &[&1_000_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+    send(&mut svm, instruction, &[&user]).expect("Unstake should succeed");
+    assert_eq!(token_balance(&svm, &user_reward_ata), 125_000);
+
+    // Payouts add up to exactly the budget
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.total_rewards_accrued, 250_000);
+    assert_eq!(pool.total_rewards_paid, 250_000);
+}
+
+#[test]
+fn apr_rewards_across_decimals() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    // A 6 decimals stake token paying a 9 decimals reward token
+    let mint = CreateMint::new(&mut svm, &admin)
+    .authority(&admin.pubkey())
+    .decimals(6)
+    .send()
+    .unwrap();
+    let (pool_pda, _bump) = get_pool_pda(&mint, &program_id);
+    let reward_mint = CreateMint::new(&mut svm, &admin)
+    .authority(&pool_pda)
+    .decimals(9)
+    .send()
+    .unwrap();
+    initialize_pool(&mut svm, &program_id, &admin, &mint, &reward_mint, 1);
+
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000_000);
+
+    // 10% APR, one stake token is worth one reward token
+    let args: [&[u8]; 3] = [&1_000u32.to_le_bytes(), &1u64.to_le_bytes(), &1u64.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_reward_apr", &args);
+    send(&mut svm, instruction, &[&admin]).expect("Set reward APR should succeed");
+
+    // Stake 1_000 whole tokens
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new_readonly(pool_pda, false)],
+        data: instruction_data("effective_apr", &[]),
+    };
+    let meta = send(&mut svm, instruction, &[&user]).expect("Effective APR should succeed");
+    assert_eq!(u64::deserialize(&mut meta.return_data.data.as_slice()).unwrap(), 1_000);
+
+    // A year later the position earned 100 whole reward tokens
+    warp_to(&mut svm, START_TIME + 31_536_000);
+
+    let instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake);
+    send(&mut svm, instruction, &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&user.pubkey(), &reward_mint)), 100_000_000_000);
+}
+
+#[test]
+fn utilization_curve_sets_the_rate() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_500_000);
+
+    let rate_curve = |kink_bps: u16| {
+        [
+            &2_000_000u128.to_le_bytes()[..],
+            &100u64.to_le_bytes(),
+            &kink_bps.to_le_bytes(),
+            &1_000u64.to_le_bytes(),
+            &2_000u64.to_le_bytes(),
+        ]
+        .concat()
+    };
+
+    // The kink must lie strictly inside the curve
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_rate_curve", &[&rate_curve(0)]);
+    assert!(send(&mut svm, instruction, &[&admin]).is_err(), "Rate curve without a kink should fail");
+
+    // 100 per second when empty, 1_000 at 50% of a 2_000_000 target and 2_000 at the target
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_rate_curve", &[&rate_curve(5_000)]);
+    send(&mut svm, instruction, &[&admin]).expect("Set rate curve should succeed");
+
+    // 75% utilization is halfway between the kink and the target
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_500_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new_readonly(pool_pda, false)],
+        data: instruction_data("effective_apr", &[]),
+    };
+    let meta = send(&mut svm, instruction, &[&user]).expect("Effective APR should succeed");
+    assert_eq!(u64::deserialize(&mut meta.return_data.data.as_slice()).unwrap(), 1_500 * 31_536_000 * 10_000 / 1_500_000);
+
+    warp_to(&mut svm, START_TIME + 100);
+
+    let instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake);
+    send(&mut svm, instruction, &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&user.pubkey(), &reward_mint)), 150_000);
+}
+
+#[test]
+fn epoch_rewards_go_to_shares_held_for_the_epoch() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let early = Keypair::new();
+    let late = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let epoch_record = get_epoch_record_pda(&pool_pda, 1, &program_id);
+    fund_user(&mut svm, &admin, &early, &mint, 1_000_000);
+    fund_user(&mut svm, &admin, &late, &mint, 2_000_000);
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &early.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&early]).expect("Stake should succeed");
+
+    // 100 second epochs, the first one starts now
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_epoch_mode", &[&100i64.to_le_bytes()]);
+    send(&mut svm, instruction, &[&admin]).expect("Set epoch mode should succeed");
+
+    // Shares staked during the epoch do not earn from it
+    warp_to(&mut svm, START_TIME + 10);
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &late.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&late]).expect("Stake should succeed");
+
+    let advance_epoch = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(admin.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(epoch_record, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("advance_epoch", &[]),
+    };
+    assert!(send(&mut svm, advance_epoch.clone(), &[&admin]).is_err(), "Advance before the epoch ends should fail");
+
+    // Between the end of the epoch and its advance the shares are frozen
+    warp_to(&mut svm, START_TIME + 100);
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &late.pubkey(), 1_000_000, 0, 0);
+    assert!(send(&mut svm, instruction, &[&late]).is_err(), "Stake in an ended epoch should fail");
+
+    send(&mut svm, advance_epoch, &[&admin]).expect("Advance epoch should succeed");
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.current_epoch, 2);
+    assert_eq!(pool.epoch_start, START_TIME + 100);
+    assert_eq!(pool.epoch_eligible_shares, 2_000_000);
+
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+    let claim_epoch = |user: &Pubkey, epoch: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(*user, true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(get_user_stake_pda(&pool_pda, user, 0, &program_id), false),
+            AccountMeta::new_readonly(program_id, false), // position_nft
+            AccountMeta::new(get_epoch_record_pda(&pool_pda, epoch, &program_id), false),
+            AccountMeta::new_readonly(*user, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(reward_mint, false),
+            AccountMeta::new(get_ata(user, &reward_mint), false),
+            AccountMeta::new_readonly(program_id, false), // reward_vesting
+            AccountMeta::new_readonly(program_id, false), // vesting_vault
+            AccountMeta::new_readonly(program_id, false), // referrer_account
+            AccountMeta::new_readonly(program_id, false), // treasury_reward_ata
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ata_program, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("claim_epoch", &[&epoch.to_le_bytes(), &[0]]),
+    };
+
+    // The whole first epoch goes to the early staker
+    send(&mut svm, claim_epoch(&early.pubkey(), 1), &[&early]).expect("Claim epoch should succeed");
+    send(&mut svm, claim_epoch(&late.pubkey(), 1), &[&late]).expect("Claim epoch should succeed");
+
+    assert_eq!(token_balance(&svm, &get_ata(&early.pubkey(), &reward_mint)), 100_000);
+    assert_eq!(token_balance(&svm, &get_ata(&late.pubkey(), &reward_mint)), 0);
+
+    // An epoch is claimed once
+    assert!(send(&mut svm, claim_epoch(&early.pubkey(), 1), &[&early]).is_err(), "Second claim should fail");
+}
+
+#[test]
+fn scheduled_start_and_pre_deposits() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    // A pool scheduled to start in 1_000 seconds
+    let mint = create_token_mint(&mut svm, &admin);
+    let (pool_pda, _bump) = get_pool_pda(&mint, &program_id);
+    let reward_mint = CreateMint::new(&mut svm, &admin).authority(&pool_pda).decimals(9).send().unwrap();
+    let instruction = initialize_pool_instruction(&program_id, &admin.pubkey(), &mint, &reward_mint, 1_000, START_TIME + 1_000);
+    send(&mut svm, instruction, &[&admin]).expect("Initialize pool should succeed");
+
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.start_time, START_TIME + 1_000);
+    assert!(!pool.allow_pre_deposit);
+
+    let set_start_time = |start_time: i64, allow_pre_deposit: bool| {
+        admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_start_time", &[&start_time.to_le_bytes(), &[allow_pre_deposit as u8]])
+    };
+
+    // No deposits before the start unless pre-deposits are allowed
+    let stake = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    assert!(send(&mut svm, stake.clone(), &[&user]).is_err(), "Stake before the start should fail");
+
+    // Nor can the start move into the past
+    assert!(send(&mut svm, set_start_time(START_TIME - 1, true), &[&admin]).is_err(), "Start in the past should fail");
+
+    send(&mut svm, set_start_time(START_TIME + 500, true), &[&admin]).expect("Set start time should succeed");
+    send(&mut svm, stake, &[&user]).expect("Pre-deposit should succeed");
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.start_time, START_TIME + 500);
+    assert!(pool.allow_pre_deposit);
+    assert_eq!(pool.total_stake, 1_000_000);
+
+    // The start can not be rescheduled once it has passed
+    warp_to(&mut svm, START_TIME + 500);
+    assert!(send(&mut svm, set_start_time(START_TIME + 1_000, true), &[&admin]).is_err(), "Rescheduling a started pool should fail");
+
+    // Rewards only accrue from the start, not from the deposit
+    warp_to(&mut svm, START_TIME + 600);
+    let instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake);
+    send(&mut svm, instruction, &[&user]).expect("Claim should succeed");
+    assert_eq!(token_balance(&svm, &get_ata(&user.pubkey(), &reward_mint)), 100_000);
+}
+
+#[test]
+fn deposit_window_and_capacity_limits() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let other = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    fund_user(&mut svm, &admin, &user, &mint, 2_000_000);
+    fund_user(&mut svm, &admin, &other, &mint, 2_000_000);
+    CreateAssociatedTokenAccount::new(&mut svm, &other, &reward_mint).send().unwrap();
+
+    let set_deposit_limits = |deposit_open: i64, deposit_close: i64| {
+        let args: [&[u8]; 5] = [
+            &deposit_open.to_le_bytes(),
+            &deposit_close.to_le_bytes(),
+            &2_500_000u128.to_le_bytes(),
+            &1_500_000u128.to_le_bytes(),
+            &100_000u64.to_le_bytes(),
+        ];
+        admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_deposit_limits", &args)
+    };
+    let stake = |user: &Pubkey, amount: u64| stake_instruction(&program_id, &pool_pda, &mint, user, amount, 0, 0);
+
+    // The window must close after it opens
+    let instruction = set_deposit_limits(START_TIME + 1_000, START_TIME + 100);
+    assert!(send(&mut svm, instruction, &[&admin]).is_err(), "Inverted deposit window should fail");
+
+    // Deposits in [100, 1_000), at least 100_000 each, up to 1_500_000 per user and 2_500_000 in total
+    send(&mut svm, set_deposit_limits(START_TIME + 100, START_TIME + 1_000), &[&admin]).expect("Set deposit limits should succeed");
+
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.deposit_open, START_TIME + 100);
+    assert_eq!(pool.deposit_close, START_TIME + 1_000);
+    assert_eq!(pool.max_total_stake, 2_500_000);
+    assert_eq!(pool.max_user_stake, 1_500_000);
+    assert_eq!(pool.min_stake_amount, 100_000);
+
+    assert!(send(&mut svm, stake(&user.pubkey(), 1_000_000), &[&user]).is_err(), "Stake before the window opens should fail");
+
+    warp_to(&mut svm, START_TIME + 100);
+    assert!(send(&mut svm, stake(&user.pubkey(), 99_999), &[&user]).is_err(), "Stake below the minimum should fail");
+    assert!(send(&mut svm, stake(&user.pubkey(), 1_500_001), &[&user]).is_err(), "Stake above the user cap should fail");
+
+    // Top-ups count towards the user cap
+    send(&mut svm, stake(&user.pubkey(), 1_000_000), &[&user]).expect("Stake should succeed");
+    send(&mut svm, stake(&user.pubkey(), 500_000), &[&user]).expect("Top-up up to the user cap should succeed");
+    assert!(send(&mut svm, stake(&user.pubkey(), 100_000), &[&user]).is_err(), "Top-up above the user cap should fail");
+
+    // The pool cap binds before the other user's own cap
+    assert!(send(&mut svm, stake(&other.pubkey(), 1_000_001), &[&other]).is_err(), "Stake above the pool cap should fail");
+    send(&mut svm, stake(&other.pubkey(), 1_000_000), &[&other]).expect("Stake up to the pool cap should succeed");
+    assert_eq!(read_pool(&svm, &pool_pda).total_stake, 2_500_000);
+
+    // Unstaking frees capacity, but only while the window is open
+    let other_stake = get_user_stake_pda(&pool_pda, &other.pubkey(), 0, &program_id);
+    let data = instruction_data("unstake", &[&500_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &other.pubkey(), &other_stake, data);
+    send(&mut svm, instruction, &[&other]).expect("Unstake should succeed");
+
+    warp_to(&mut svm, START_TIME + 1_000);
+    assert!(send(&mut svm, stake(&other.pubkey(), 100_000), &[&other]).is_err(), "Stake after the window closes should fail");
+}
+
+#[test]
+fn term_deposit_and_redeem() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let (term_product, term_vault, term_reward_vault) = get_term_pdas(&pool_pda, 1, &program_id);
+    let fee_vault = get_fee_vault_pda(&pool_pda, &program_id);
+    let user_ata = fund_user(&mut svm, &admin, &user, &mint, 2_000_000);
+    let user_reward_ata = get_ata(&user.pubkey(), &reward_mint);
+
+    // A one year product at 10% APY, redeemable early for a 5% penalty
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(admin.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(reward_mint, false),
+            AccountMeta::new(term_product, false),
+            AccountMeta::new(term_vault, false),
+            AccountMeta::new(term_reward_vault, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data(
+            "create_term_product",
+            &[&1u16.to_le_bytes(), &31_536_000i64.to_le_bytes(), &1_000u32.to_le_bytes(), &[1], &500u16.to_le_bytes()],
+        ),
+    };
+    send(&mut svm, instruction, &[&admin]).expect("Create term product should succeed");
+
+    let open_term_deposit = |position_id: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new(term_product, false),
+            AccountMeta::new(get_term_position_pda(&term_product, position_id, &program_id), false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(user_ata, false),
+            AccountMeta::new(term_vault, false),
+            AccountMeta::new_readonly(term_reward_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("open_term_deposit", &[&1_000_000u64.to_le_bytes(), &1u16.to_le_bytes(), &[0]]),
+    };
+
+    // The interest must be funded before deposits open
+    assert!(send(&mut svm, open_term_deposit(0), &[&user]).is_err(), "Unfunded deposit should fail");
+
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(admin.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(reward_mint, false),
+            AccountMeta::new_readonly(term_product, false),
+            AccountMeta::new(term_reward_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+        ],
+        data: instruction_data("fund_term_rewards", &[&200_000u64.to_le_bytes()]),
+    };
+    send(&mut svm, instruction, &[&admin]).expect("Fund term rewards should succeed");
+
+    // Each deposit reserves 100_000 of interest
+    send(&mut svm, open_term_deposit(0), &[&user]).expect("Open term deposit should succeed");
+    send(&mut svm, open_term_deposit(1), &[&user]).expect("Open term deposit should succeed");
+    assert_eq!(token_balance(&svm, &term_vault), 2_000_000);
+
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+    let redeem_term = |position_id: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new_readonly(pool_pda, false),
+            AccountMeta::new(term_product, false),
+            AccountMeta::new(get_term_position_pda(&term_product, position_id, &program_id), false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(reward_mint, false),
+            AccountMeta::new(user_ata, false),
+            AccountMeta::new(user_reward_ata, false),
+            AccountMeta::new(term_vault, false),
+            AccountMeta::new(term_reward_vault, false),
+            AccountMeta::new(fee_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ata_program, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("redeem_term", &[&[0]]),
+    };
+
+    // Redeeming early forfeits the interest and pays the penalty into the fee vault
+    warp_to(&mut svm, START_TIME + 1_000);
+    send(&mut svm, redeem_term(1), &[&user]).expect("Early redeem should succeed");
+
+    assert_eq!(token_balance(&svm, &user_ata), 950_000);
+    assert_eq!(token_balance(&svm, &fee_vault), 50_000);
+    assert_eq!(token_balance(&svm, &user_reward_ata), 0);
+
+    // At maturity the principal comes back with the interest
+    warp_to(&mut svm, START_TIME + 31_536_000);
+    send(&mut svm, redeem_term(0), &[&user]).expect("Redeem at maturity should succeed");
+
+    assert_eq!(token_balance(&svm, &user_ata), 1_950_000);
+    assert_eq!(token_balance(&svm, &user_reward_ata), 100_000);
+    assert_eq!(token_balance(&svm, &term_vault), 0);
+    assert_eq!(token_balance(&svm, &term_reward_vault), 100_000);
+    assert!(svm.get_account(&get_term_position_pda(&term_product, 0, &program_id)).is_none_or(|account| account.lamports == 0));
+}
+
+#[test]
+fn claim_several_positions_at_once() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let other = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 5_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let positions = [0, 1].map(|position_index| get_user_stake_pda(&pool_pda, &user.pubkey(), position_index, &program_id));
+    let other_stake = get_user_stake_pda(&pool_pda, &other.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 5_000_000);
+    fund_user(&mut svm, &admin, &other, &mint, 1_000_000);
+
+    // Positions are opened in order
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 1);
+    assert!(send(&mut svm, instruction, &[&user]).is_err(), "Stake skipping a position index should fail");
+
+    for (position_index, amount) in [(0u64, 1_000_000u64), (1u64, 3_000_000u64)] {
+        let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), amount, 0, position_index);
+        send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+    }
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &other.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&other]).expect("Stake should succeed");
+
+    assert_eq!(read_user_stake(&svm, &positions[0]).shares, 1_000_000);
+    assert_eq!(read_user_stake(&svm, &positions[1]).shares, 3_000_000);
+    assert_eq!(read_user_stake(&svm, &positions[1]).position_index, 1);
+
+    // The positions earn 100_000 and 300_000 over 100 seconds
+    warp_to(&mut svm, START_TIME + 100);
+
+    // Another wallet's position can not be claimed along
+    let mut instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &positions[0]);
+    instruction.accounts.push(AccountMeta::new(other_stake, false));
+    assert!(send(&mut svm, instruction, &[&user]).is_err(), "Claim of another wallet's position should fail");
+
+    let mut instruction = claim_reward_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &positions[0]);
+    instruction.accounts.push(AccountMeta::new(positions[1], false));
+    send(&mut svm, instruction, &[&user]).expect("Claim should succeed");
+
+    assert_eq!(token_balance(&svm, &get_ata(&user.pubkey(), &reward_mint)), 400_000);
+    assert_eq!(read_pool(&svm, &pool_pda).total_rewards_paid, 400_000);
+}
+
+#[test]
+fn split_transfer_and_merge_positions() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let recipient = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+    svm.airdrop(&recipient.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, _reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let positions = [0, 1].map(|position_index| get_user_stake_pda(&pool_pda, &user.pubkey(), position_index, &program_id));
+    let recipient_stake = get_user_stake_pda(&pool_pda, &recipient.pubkey(), 0, &program_id);
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    // Split 400_000 shares into the next position
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(positions[0], false),
+            AccountMeta::new(get_position_counter_pda(&pool_pda, &user.pubkey(), &program_id), false),
+            AccountMeta::new(positions[1], false),
+            AccountMeta::new_readonly(program_id, false), // position_mint
+            AccountMeta::new_readonly(program_id, false), // position_nft
+            AccountMeta::new_readonly(program_id, false), // position_token_program
+            AccountMeta::new_readonly(program_id, false), // associated_token_program
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data("split_position", &[&400_000u128.to_le_bytes(), &[0]]),
+    };
+    send(&mut svm, instruction, &[&user]).expect("Split position should succeed");
+
+    assert_eq!(read_user_stake(&svm, &positions[0]).shares, 600_000);
+    assert_eq!(read_user_stake(&svm, &positions[1]).shares, 400_000);
+
+    let transfer_position = |shares: u128| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(positions[1], false),
+            AccountMeta::new(recipient_stake, false),
+            AccountMeta::new(get_position_counter_pda(&pool_pda, &user.pubkey(), &program_id), false),
+            AccountMeta::new(get_position_counter_pda(&pool_pda, &recipient.pubkey(), &program_id), false),
+            AccountMeta::new_readonly(program_id, false), // recipient
+            AccountMeta::new_readonly(program_id, false), // recipient_wallet
+            AccountMeta::new_readonly(program_id, false), // position_mint
+            AccountMeta::new_readonly(program_id, false), // position_nft
+            AccountMeta::new_readonly(program_id, false), // position_token_program
+            AccountMeta::new_readonly(program_id, false), // associated_token_program
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data(
+            "transfer_position",
+            &[&shares.to_le_bytes(), recipient.pubkey().as_ref(), &0u64.to_le_bytes(), &[0]],
+        ),
+    };
+
+    // Transfer 100_000 shares into a new position of the recipient
+    send(&mut svm, transfer_position(100_000), &[&user]).expect("Transfer position should succeed");
+
+    let received = read_user_stake(&svm, &recipient_stake);
+    assert_eq!(received.owner, recipient.pubkey());
+    assert_eq!(received.shares, 100_000);
+    assert_eq!(read_user_stake(&svm, &positions[1]).shares, 300_000);
+
+    // Adding to the recipient's existing position needs their signature
+    assert!(send(&mut svm, transfer_position(100_000), &[&user]).is_err(), "Unsigned transfer into a position should fail");
+
+    let mut instruction = transfer_position(100_000);
+    instruction.accounts[6] = AccountMeta::new_readonly(recipient.pubkey(), true);
+    send(&mut svm, instruction, &[&user, &recipient]).expect("Signed transfer into a position should succeed");
+    assert_eq!(read_user_stake(&svm, &recipient_stake).shares, 200_000);
+
+    // Merge the rest of the split position back, closing it
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(user.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(positions[1], false),
+            AccountMeta::new(positions[0], false),
+        ],
+        data: instruction_data("merge_positions", &[&[0]]),
+    };
+    send(&mut svm, instruction, &[&user]).expect("Merge positions should succeed");
+
+    assert_eq!(read_user_stake(&svm, &positions[0]).shares, 800_000);
+    assert!(svm.get_account(&positions[1]).is_none_or(|account| account.lamports == 0));
+
+    // Moving shares never changes the pool's totals
+    let pool = read_pool(&svm, &pool_pda);
+    assert_eq!(pool.total_shares, 1_000_000);
+    assert_eq!(pool.total_stake, 1_000_000);
+}
+
+#[test]
+fn user_cap_spans_all_positions_of_a_wallet() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let other = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let user_counter = get_position_counter_pda(&pool_pda, &user.pubkey(), &program_id);
+    let other_counter = get_position_counter_pda(&pool_pda, &other.pubkey(), &program_id);
+    let other_ata = fund_user(&mut svm, &admin, &other, &mint, 2_000_000);
+    fund_user(&mut svm, &admin, &user, &mint, 2_000_000);
+    CreateAssociatedTokenAccount::new(&mut svm, &user, &reward_mint).send().unwrap();
+
+    // Each wallet may hold up to 1_000_000 of principal
+    let args: [&[u8]; 5] = [&0i64.to_le_bytes(), &0i64.to_le_bytes(), &0u128.to_le_bytes(), &1_000_000u128.to_le_bytes(), &0u64.to_le_bytes()];
+    let instruction = admin_instruction(&program_id, &admin.pubkey(), &pool_pda, "set_deposit_limits", &args);
+    send(&mut svm, instruction, &[&admin]).expect("Set deposit limits should succeed");
+
+    let stake = |user: &Pubkey, amount: u64, position_index: u64| {
+        stake_instruction(&program_id, &pool_pda, &mint, user, amount, 0, position_index)
+    };
+
+    // Opening another position does not reset the cap
+    send(&mut svm, stake(&user.pubkey(), 600_000, 0), &[&user]).expect("Stake should succeed");
+    assert!(send(&mut svm, stake(&user.pubkey(), 500_000, 1), &[&user]).is_err(), "Second position above the cap should fail");
+    send(&mut svm, stake(&user.pubkey(), 400_000, 1), &[&user]).expect("Second position up to the cap should succeed");
+
+    // Nor does having another wallet fund the position
+    let stake_for = |amount: u64, position_index: u64| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(other.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(other_ata, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(get_user_stake_pda(&pool_pda, &user.pubkey(), position_index, &program_id), false),
+            AccountMeta::new(user_counter, false),
+            AccountMeta::new_readonly(program_id, false), // fee_vault
+            AccountMeta::new_readonly(program_id, false), // beneficiary_wallet
+            AccountMeta::new_readonly(program_id, false), // position_mint
+            AccountMeta::new_readonly(program_id, false), // position_nft
+            AccountMeta::new_readonly(program_id, false), // position_token_program
+            AccountMeta::new_readonly(program_id, false), // associated_token_program
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data(
+            "stake_for",
+            &[user.pubkey().as_ref(), &amount.to_le_bytes(), &position_index.to_le_bytes(), &0u128.to_le_bytes(), &[0]],
+        ),
+    };
+    assert!(send(&mut svm, stake_for(100_000, 2), &[&other]).is_err(), "Stake for a wallet at the cap should fail");
+
+    // Nor receiving shares from another wallet
+    send(&mut svm, stake(&other.pubkey(), 500_000, 0), &[&other]).expect("Stake should succeed");
+
+    let transfer_position = |shares: u128| Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(other.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(get_user_stake_pda(&pool_pda, &other.pubkey(), 0, &program_id), false),
+            AccountMeta::new(get_user_stake_pda(&pool_pda, &user.pubkey(), 2, &program_id), false),
+            AccountMeta::new(other_counter, false),
+            AccountMeta::new(user_counter, false),
+            AccountMeta::new_readonly(program_id, false), // recipient
+            AccountMeta::new_readonly(program_id, false), // recipient_wallet
+            AccountMeta::new_readonly(program_id, false), // position_mint
+            AccountMeta::new_readonly(program_id, false), // position_nft
+            AccountMeta::new_readonly(program_id, false), // position_token_program
+            AccountMeta::new_readonly(program_id, false), // associated_token_program
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data(
+            "transfer_position",
+            &[&shares.to_le_bytes(), user.pubkey().as_ref(), &2u64.to_le_bytes(), &[0]],
+        ),
+    };
+    assert!(send(&mut svm, transfer_position(100_000), &[&other]).is_err(), "Transfer to a wallet at the cap should fail");
+
+    // Withdrawing from either position frees capacity for the wallet
+    let data = instruction_data("unstake", &[&300_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+    send(&mut svm, instruction, &[&user]).expect("Unstake should succeed");
+
+    // The sender's counter must be passed for its principal to move along
+    let mut instruction = transfer_position(200_000);
+    instruction.accounts[4] = AccountMeta::new_readonly(program_id, false);
+    assert!(send(&mut svm, instruction, &[&other]).is_err(), "Transfer without the sender's counter should fail");
+
+    send(&mut svm, transfer_position(200_000), &[&other]).expect("Transfer up to the cap should succeed");
+    send(&mut svm, stake_for(100_000, 2), &[&other]).expect("Stake for up to the cap should succeed");
+    assert!(send(&mut svm, stake(&user.pubkey(), 1, 0), &[&user]).is_err(), "Stake above the cap should fail");
+
+    // The moved principal left the sender's counter
+    send(&mut svm, stake(&other.pubkey(), 700_000, 0), &[&other]).expect("Stake up to the cap should succeed");
+    assert_eq!(read_pool(&svm, &pool_pda).total_stake, 2_000_000);
+}
+
+#[test]
+fn position_nft_gates_unstake_and_burns_on_exit() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let holder = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let (position_mint, user_nft) = get_position_nft_pdas(&user_stake, &user.pubkey(), &program_id);
+    let (_, holder_nft) = get_position_nft_pdas(&user_stake, &holder.pubkey(), &program_id);
+    let token_2022_program: Pubkey = TOKEN_2022_PROGRAM_ID.parse().unwrap();
+    let ata_program: Pubkey = ASSOCIATED_TOKEN_PROGRAM_ID.parse().unwrap();
+
+    fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    let holder_ata = fund_user(&mut svm, &admin, &holder, &mint, 0);
+    CreateAssociatedTokenAccount::new(&mut svm, &user, &reward_mint).send().unwrap();
+    CreateAssociatedTokenAccount::new(&mut svm, &holder, &reward_mint).send().unwrap();
+
+    // Open the position as an NFT
+    let mut instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    instruction.accounts[9] = AccountMeta::new(position_mint, false);
+    instruction.accounts[10] = AccountMeta::new(user_nft, false);
+    instruction.accounts[11] = AccountMeta::new_readonly(token_2022_program, false);
+    instruction.accounts[12] = AccountMeta::new_readonly(ata_program, false);
+    send(&mut svm, instruction, &[&user]).expect("Stake as an NFT should succeed");
+
+    assert_eq!(read_user_stake(&svm, &user_stake).position_mint, position_mint);
+    assert_eq!(token_balance(&svm, &user_nft), 1);
+
+    // Hand the NFT over
+    CreateAssociatedTokenAccount::new(&mut svm, &holder, &position_mint)
+    .token_program_id(&token_2022_program)
+    .send()
+    .unwrap();
+
+    let mut transfer_data = vec![12]; // TransferChecked
+    transfer_data.extend_from_slice(&1u64.to_le_bytes());
+    transfer_data.push(0);
+    let instruction = Instruction {
+        program_id: token_2022_program,
+        accounts: vec![
+            AccountMeta::new(user_nft, false),
+            AccountMeta::new_readonly(position_mint, false),
+            AccountMeta::new(holder_nft, false),
+            AccountMeta::new_readonly(user.pubkey(), true),
+        ],
+        data: transfer_data,
+    };
+    send(&mut svm, instruction, &[&user]).expect("NFT transfer should succeed");
+
+    let unstake_all = |signer: &Pubkey, position_nft: &Pubkey| {
+        let data = instruction_data("unstake_all", &[&0u64.to_le_bytes(), &[0], &[0]]);
+        let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, signer, &user_stake, data);
+        instruction.accounts[2] = AccountMeta::new(get_position_counter_pda(&pool_pda, &user.pubkey(), &program_id), false);
+        instruction.accounts[14] = AccountMeta::new(*position_nft, false);
+        instruction.accounts[15] = AccountMeta::new(position_mint, false);
+        instruction.accounts[16] = AccountMeta::new_readonly(token_2022_program, false);
+        instruction
+    };
+
+    // The owner no longer controls the position
+    assert!(send(&mut svm, unstake_all(&user.pubkey(), &user_nft), &[&user]).is_err(), "Unstake by the owner should fail");
+
+    // The holder exits, burning the NFT
+    send(&mut svm, unstake_all(&holder.pubkey(), &holder_nft), &[&holder]).expect("Unstake by the holder should succeed");
+
+    assert_eq!(token_balance(&svm, &holder_ata), 1_000_000);
+    assert_eq!(token_balance(&svm, &holder_nft), 0);
+    assert_eq!(read_user_stake(&svm, &user_stake).position_mint, Pubkey::default());
+}
+
+#[test]
+fn stake_for_another_wallet() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let funder = Keypair::new();
+    let beneficiary = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let (stake_vault, _bump) = get_stake_vault_pda(&pool_pda, &program_id);
+    let user_stake = get_user_stake_pda(&pool_pda, &beneficiary.pubkey(), 0, &program_id);
+    let funder_ata = fund_user(&mut svm, &admin, &funder, &mint, 1_000_000);
+    let beneficiary_ata = fund_user(&mut svm, &admin, &beneficiary, &mint, 0);
+    CreateAssociatedTokenAccount::new(&mut svm, &funder, &reward_mint).send().unwrap();
+    CreateAssociatedTokenAccount::new(&mut svm, &beneficiary, &reward_mint).send().unwrap();
+
+    // The funder pays, the beneficiary owns the position
+    let instruction = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(funder.pubkey(), true),
+            AccountMeta::new(pool_pda, false),
+            AccountMeta::new(funder_ata, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(stake_vault, false),
+            AccountMeta::new(user_stake, false),
+            AccountMeta::new(get_position_counter_pda(&pool_pda, &beneficiary.pubkey(), &program_id), false),
+            AccountMeta::new_readonly(program_id, false), // fee_vault
+            AccountMeta::new_readonly(program_id, false), // beneficiary_wallet
+            AccountMeta::new_readonly(program_id, false), // position_mint
+            AccountMeta::new_readonly(program_id, false), // position_nft
+            AccountMeta::new_readonly(program_id, false), // position_token_program
+            AccountMeta::new_readonly(program_id, false), // associated_token_program
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(ID, false),
+        ],
+        data: instruction_data(
+            "stake_for",
+            &[
+                beneficiary.pubkey().as_ref(),
+                &1_000_000u64.to_le_bytes(),
+                &0u64.to_le_bytes(),
+                &0u128.to_le_bytes(),
+                &[0],
+            ],
+        ),
+    };
+    send(&mut svm, instruction, &[&funder]).expect("Stake for should succeed");
+
+    let position = read_user_stake(&svm, &user_stake);
+    assert_eq!(position.owner, beneficiary.pubkey());
+    assert_eq!(position.shares, 1_000_000);
+    assert_eq!(token_balance(&svm, &funder_ata), 0);
+
+    // Only the beneficiary can take the stake out
+    warp_to(&mut svm, START_TIME + 100);
+
+    let data = instruction_data("unstake", &[&1_000_000u128.to_le_bytes(), &0u64.to_le_bytes(), &[0]]);
+    let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &funder.pubkey(), &user_stake, data.clone());
+    instruction.accounts[2] = AccountMeta::new(get_position_counter_pda(&pool_pda, &beneficiary.pubkey(), &program_id), false);
+    assert!(send(&mut svm, instruction, &[&funder]).is_err(), "Unstake by the funder should fail");
+
+    let instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &beneficiary.pubkey(), &user_stake, data);
+    send(&mut svm, instruction, &[&beneficiary]).expect("Unstake by the beneficiary should succeed");
+
+    assert_eq!(token_balance(&svm, &beneficiary_ata), 1_000_000);
+    assert_eq!(token_balance(&svm, &get_ata(&beneficiary.pubkey(), &reward_mint)), 100_000);
+}
+
+#[test]
+fn unstake_amount_rounds_in_favour_of_the_pool() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let fee_vault = get_fee_vault_pda(&pool_pda, &program_id);
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let user_ata = fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    CreateAssociatedTokenAccount::new(&mut svm, &user, &reward_mint).send().unwrap();
+
+    // A 2% withdrawal fee
+    let instruction = set_principal_fees_instruction(&program_id, &admin.pubkey(), &pool_pda, &mint, 0, 200);
+    send(&mut svm, instruction, &[&admin]).expect("Set principal fees should succeed");
+
+    let instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+    send(&mut svm, instruction, &[&user]).expect("Stake should succeed");
+
+    let unstake_amount = |amount: u64| {
+        let data = instruction_data("unstake_amount", &[&amount.to_le_bytes(), &[0]]);
+        let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+        instruction.accounts[13] = AccountMeta::new(fee_vault, false);
+        instruction
+    };
+
+    // More than the position holds after the fee
+    assert!(send(&mut svm, unstake_amount(990_000), &[&user]).is_err(), "Unstake above the position should fail");
+
+    // Receiving exactly 100_000 takes 100_000 / 98% = 102_040.8 of stake, rounded up
+    send(&mut svm, unstake_amount(100_000), &[&user]).expect("Unstake amount should succeed");
+
+    assert_eq!(token_balance(&svm, &user_ata), 100_000);
+    assert_eq!(token_balance(&svm, &fee_vault), 2_041);
+    assert_eq!(read_user_stake(&svm, &user_stake).shares, 897_959);
+    assert_eq!(read_pool(&svm, &pool_pda).total_stake, 897_959);
+
+    // Unstake the rest and close the position
+    let data = instruction_data("unstake_all", &[&0u64.to_le_bytes(), &[1], &[0]]);
+    let mut instruction = unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data);
+    instruction.accounts[13] = AccountMeta::new(fee_vault, false);
+    send(&mut svm, instruction, &[&user]).expect("Unstake all should succeed");
+
+    assert_eq!(token_balance(&svm, &user_ata), 980_000);
+    assert_eq!(token_balance(&svm, &fee_vault), 20_000);
+    assert!(svm.get_account(&user_stake).is_none_or(|account| account.lamports == 0));
+}
+
+#[test]
+fn deadline_and_slippage_protection() {
+    let (program_id, mut svm) = deploy_staking_program();
+    warp_to(&mut svm, START_TIME);
+
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    svm.airdrop(&admin.pubkey(), 1_000_000_000).unwrap();
+
+    let (pool_pda, reward_mint) = create_pool(&mut svm, &program_id, &admin, 1_000);
+    let mint = read_pool(&svm, &pool_pda).stake_mint;
+    let user_stake = get_user_stake_pda(&pool_pda, &user.pubkey(), 0, &program_id);
+    let user_ata = fund_user(&mut svm, &admin, &user, &mint, 1_000_000);
+    CreateAssociatedTokenAccount::new(&mut svm, &user, &reward_mint).send().unwrap();
+
+    let stake = |min_shares_out: u128, deadline: i64| {
+        let mut instruction = stake_instruction(&program_id, &pool_pda, &mint, &user.pubkey(), 1_000_000, 0, 0);
+        // Replace the default min shares and deadline at the end of the arguments
+        instruction.data.truncate(instruction.data.len() - 17);
+        instruction.data.extend_from_slice(&min_shares_out.to_le_bytes());
+        instruction.data.push(1);
+        instruction.data.extend_from_slice(&deadline.to_le_bytes());
+        instruction
+    };
+
+    assert!(send(&mut svm, stake(1_000_001, START_TIME), &[&user]).is_err(), "Stake below the min shares should fail");
+    assert!(send(&mut svm, stake(1_000_000, START_TIME - 1), &[&user]).is_err(), "Expired stake should fail");
+
+    send(&mut svm, stake(1_000_000, START_TIME), &[&user]).expect("Stake at the deadline should succeed");
+    assert_eq!(read_user_stake(&svm, &user_stake).shares, 1_000_000);
+
+    let unstake = |min_amount_out: u64, deadline: i64| {
+        let data = instruction_data(
+            "unstake",
+            &[&1_000_000u128.to_le_bytes(), &min_amount_out.to_le_bytes(), &[1], &deadline.to_le_bytes()],
+        );
+        unstake_instruction(&program_id, &pool_pda, &mint, &reward_mint, &user.pubkey(), &user_stake, data)
+    };
+
+    warp_to(&mut svm, START_TIME + 10);
+
+    assert!(send(&mut svm, unstake(1_000_001, START_TIME + 10), &[&user]).is_err(), "Unstake below the min amount should fail");
+    assert!(send(&mut svm, unstake(1_000_000, START_TIME + 9), &[&user]).is_err(), "Expired unstake should fail");
+    assert_eq!(token_balance(&svm, &user_ata), 0);
+
+    send(&mut svm, unstake(1_000_000, START_TIME + 10), &[&user]).expect("Unstake should succeed");
+    assert_eq!(token_balance(&svm, &user_ata), 1_000_000);
+}